@@ -0,0 +1,291 @@
+//! How a body is drawn.
+//!
+//! In a system file the appearance is either the path of a glTF model (relative to
+//! `assets/models`) or a map:
+//!
+//! ```yaml
+//! appearance:
+//!   model: earth/earth.gltf
+//!   scene: 0                # the scene drawn, Scene0 by default
+//!   material:
+//!     roughness: 0.8
+//!   scale: 1.02             # for models that are not a unit sphere
+//!   clouds:
+//!     texture: earth/clouds.png
+//!     height: 10 km
+//!   atmosphere: [0.5, 0.7, 1.0, 0.15]
+//! ```
+//!
+//! `mesh: 1` draws only `Mesh1/Primitive0` of the model instead of a scene. Material overrides
+//! apply to all materials of a scene. Stars are light sources, other bodies can declare themselves
+//! one with `light_source: true`. Without a model (or with `none`) the body is drawn procedurally,
+//! see `procedural`.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{quantity::Length, units, BodyKind};
+
+/// Overrides of the model's material
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialSpec {
+    /// the glTF material (`Material<index>`) of a single mesh and of the low detail sphere
+    #[serde(default)]
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_color: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emissive: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<f32>,
+}
+
+impl MaterialSpec {
+    pub fn has_overrides(&self) -> bool {
+        self.base_color.is_some() || self.emissive.is_some() || self.roughness.is_some()
+    }
+
+    /// Apply the overrides. Light sources glow in their own colors unless `emissive` is given.
+    pub fn apply(&self, material: &mut StandardMaterial, light_source: bool) {
+        if let Some([r, g, b]) = self.base_color {
+            material.base_color = Color::rgb(r, g, b);
+        }
+        if let Some([r, g, b]) = self.emissive {
+            material.emissive = Color::rgb(r, g, b);
+        } else if light_source {
+            material.emissive = material.base_color;
+            material.emissive_texture = material.base_color_texture.clone();
+        }
+        if let Some(roughness) = self.roughness {
+            material.perceptual_roughness = roughness;
+        }
+    }
+}
+
+/// A translucent shell above the surface
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CloudLayer {
+    /// image relative to `assets/models`, its alpha channel is the cloud cover
+    pub texture: String,
+    /// above the surface
+    #[serde(with = "units::km")]
+    pub height: Length,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Appearance {
+    /// glTF file relative to `assets/models`, None for a procedural appearance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// the scene of the model drawn, `Scene<scene>`
+    pub scene: usize,
+    /// draw only the mesh primitive `Mesh<mesh>/Primitive<primitive>` instead of the scene
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<usize>,
+    pub primitive: usize,
+    pub material: MaterialSpec,
+    /// multiplies the body's radius for the model
+    pub scale: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clouds: Option<CloudLayer>,
+    /// RGBA tint of the atmosphere shell, up to the body's `atmosphere_height`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atmosphere: Option<[f32; 4]>,
+    /// defaults to true for stars
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_source: Option<bool>,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Appearance {
+            model: None,
+            scene: 0,
+            mesh: None,
+            primitive: 0,
+            material: MaterialSpec::default(),
+            scale: 1.0,
+            clouds: None,
+            atmosphere: None,
+            light_source: None,
+        }
+    }
+}
+
+/// a bare model path, `none` (or an empty string) for a procedural appearance
+impl From<&str> for Appearance {
+    fn from(model: &str) -> Self {
+        Appearance {
+            model: (!model.is_empty() && model != "none").then(|| model.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Appearance {
+    /// model file relative to the asset root
+    pub fn model_path(&self) -> Option<String> {
+        self.model.as_ref().map(|model| format!("models/{}", model))
+    }
+
+    /// None unless a single mesh is selected
+    pub fn mesh_path(&self) -> Option<String> {
+        let model = self.model_path()?;
+        Some(format!(
+            "{}#Mesh{}/Primitive{}",
+            model, self.mesh?, self.primitive
+        ))
+    }
+
+    /// None if a single mesh is selected
+    pub fn scene_path(&self) -> Option<String> {
+        let model = self.model_path()?;
+        self.mesh
+            .is_none()
+            .then(|| format!("{}#Scene{}", model, self.scene))
+    }
+
+    pub fn material_path(&self) -> Option<String> {
+        let model = self.model_path()?;
+        Some(format!("{}#Material{}", model, self.material.index))
+    }
+
+    pub fn is_light_source(&self, kind: BodyKind) -> bool {
+        self.light_source.unwrap_or(kind == BodyKind::Star)
+    }
+
+    /// only a model path, written as a plain string
+    fn is_plain(&self) -> bool {
+        *self == Appearance::from(self.model.as_deref().unwrap_or_default())
+    }
+}
+
+// (de)serializes plain model paths as well as full appearances, for use with #[serde(with = "...")]
+pub mod path_or_spec {
+    use std::fmt;
+
+    use serde::{
+        de::{self, value::MapAccessDeserializer, MapAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::Appearance;
+
+    struct PathOrSpec;
+
+    // by hand rather than an untagged enum, which would hide the errors inside a map
+    impl<'de> Visitor<'de> for PathOrSpec {
+        type Value = Appearance;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a model path or an appearance")
+        }
+
+        fn visit_str<E: de::Error>(self, path: &str) -> Result<Appearance, E> {
+            Ok(Appearance::from(path))
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Appearance, M::Error> {
+            Appearance::deserialize(MapAccessDeserializer::new(map))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Appearance, D::Error> {
+        deserializer.deserialize_any(PathOrSpec)
+    }
+
+    pub fn serialize<S: Serializer>(value: &Appearance, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_plain() {
+            value
+                .model
+                .as_deref()
+                .unwrap_or("none")
+                .serialize(serializer)
+        } else {
+            value.serialize(serializer)
+        }
+    }
+}
+
+#[test]
+fn test_appearance() {
+    #[derive(Serialize, Deserialize)]
+    struct Body {
+        #[serde(with = "path_or_spec")]
+        appearance: Appearance,
+    }
+
+    let plain: Body = serde_yaml::from_str("appearance: earth/earth.gltf").unwrap();
+    assert_eq!(plain.appearance, Appearance::from("earth/earth.gltf"));
+    assert_eq!(
+        plain.appearance.scene_path().unwrap(),
+        "models/earth/earth.gltf#Scene0"
+    );
+    assert_eq!(plain.appearance.mesh_path(), None);
+    assert_eq!(
+        plain.appearance.material_path().unwrap(),
+        "models/earth/earth.gltf#Material0"
+    );
+    assert_eq!(
+        serde_yaml::to_string(&plain).unwrap(),
+        "---\nappearance: earth/earth.gltf\n"
+    );
+    let none: Body = serde_yaml::from_str("appearance: none").unwrap();
+    assert_eq!(none.appearance.scene_path(), None);
+
+    let spec: Body = serde_yaml::from_str(
+        r#"
+appearance:
+  model: earth/earth.gltf
+  mesh: 1
+  material: { index: 2, roughness: 0.8 }
+  scale: 1.02
+  clouds: { texture: earth/clouds.png, height: 10 km }
+  atmosphere: [0.5, 0.7, 1.0, 0.15]
+"#,
+    )
+    .unwrap();
+    let appearance = &spec.appearance;
+    assert_eq!(
+        appearance.mesh_path().unwrap(),
+        "models/earth/earth.gltf#Mesh1/Primitive0"
+    );
+    assert_eq!(appearance.scene_path(), None);
+    assert_eq!(
+        appearance.material_path().unwrap(),
+        "models/earth/earth.gltf#Material2"
+    );
+    assert_eq!(appearance.material.roughness, Some(0.8));
+    assert_eq!(
+        appearance.clouds.as_ref().unwrap().height,
+        Length::from_km(10.0)
+    );
+    assert!(!appearance.is_light_source(BodyKind::Planet));
+    assert!(appearance.is_light_source(BodyKind::Star));
+    // survives a round trip
+    let yaml = serde_yaml::to_string(&spec).unwrap();
+    assert_eq!(
+        serde_yaml::from_str::<Body>(&yaml).unwrap().appearance,
+        *appearance
+    );
+
+    let scene: Body =
+        serde_yaml::from_str("appearance: { model: sun/sun.gltf, scene: 1 }").unwrap();
+    assert_eq!(
+        scene.appearance.scene_path().unwrap(),
+        "models/sun/sun.gltf#Scene1"
+    );
+
+    // errors inside an appearance are not swallowed
+    let error = serde_yaml::from_str::<Body>(
+        "appearance: { model: earth/earth.gltf, clouds: { texture: x.png, height: 10 parsec } }",
+    )
+    .err()
+    .unwrap()
+    .to_string();
+    assert!(error.contains("unknown length unit 'parsec'"), "{}", error);
+    assert!(serde_yaml::from_str::<Body>("appearance: { model: x.gltf, scael: 2 }").is_err());
+    assert!(serde_yaml::from_str::<Body>("appearance: { material: { roughnes: 0.5 } }").is_err());
+}
@@ -0,0 +1,206 @@
+//! Asteroid belts and ring systems.
+//!
+//! A belt is described by a handful of parameters in the system file and expands deterministically
+//! (from its seed) into many small rocks. Rocks are not `Body`s: they have no satellites or
+//! physical properties, only a name, a radius and orbital elements around the belt's parent.
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    orbit::OrbitalElements,
+    physical,
+    quantity::{Angle, Length},
+    units, Body,
+};
+
+fn default_exponent() -> f32 {
+    3.5
+}
+
+/// Power-law size distribution, the number of rocks with radius r is proportional to r^-exponent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SizeDistribution {
+    #[serde(with = "units::km")]
+    pub min: Length,
+    #[serde(with = "units::km")]
+    pub max: Length,
+    #[serde(default = "default_exponent")]
+    pub exponent: f32,
+}
+
+impl SizeDistribution {
+    /// inverse transform sampling of the power law, `u` in [0, 1)
+    fn sample(&self, u: f64) -> Length {
+        let (min, max) = (self.min.as_km(), self.max.as_km());
+        let k = 1.0 - self.exponent as f64;
+        let km = if k.abs() < 1e-6 {
+            // exponent 1: log-uniform
+            min * (max / min).powf(u)
+        } else {
+            (min.powf(k) + u * (max.powf(k) - min.powf(k))).powf(1.0 / k)
+        };
+        Length::from_km(km)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Belt {
+    pub name: String,
+    /// inner edge (semi-major axis)
+    #[serde(with = "units::au")]
+    pub inner: Length,
+    /// outer edge (semi-major axis)
+    #[serde(with = "units::au")]
+    pub outer: Length,
+    pub count: u32,
+    pub size: SizeDistribution,
+    /// maximum angle between a rock's orbit and the mean plane of the belt
+    #[serde(default, with = "units::degrees")]
+    pub inclination_spread: Angle,
+    #[serde(default)]
+    pub max_eccentricity: f32,
+    /// the mean plane is the parent's equator (tilted by its `axial_tilt`) instead of the
+    /// reference plane, as for ring systems
+    #[serde(default)]
+    pub equatorial: bool,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub appearance: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rock {
+    pub name: String,
+    pub radius: Length,
+    pub elements: OrbitalElements,
+}
+
+impl Belt {
+    /// Expand the belt into individual rocks orbiting `parent`. The same seed always produces the
+    /// same rocks. None if the parent has neither `mass` nor `gm`, as the orbital periods are
+    /// derived from it.
+    pub fn rocks(&self, parent: &Body) -> Option<Vec<Rock>> {
+        let gm = parent.gm()?;
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+
+        // mean plane, as the inclination and node of its normal
+        let (plane_inclination, plane_node) = if self.equatorial {
+            // the spin axis is tilted around the bevy X axis, which is the line of nodes at 90°
+            (parent.axial_tilt.as_radians(), 90f64.to_radians())
+        } else {
+            (0.0, 0.0)
+        };
+        let normal = plane_normal(plane_inclination, plane_node);
+        let node_axis = [plane_node.cos(), plane_node.sin(), 0.0];
+        let in_plane = cross(normal, node_axis);
+        let spread = self.inclination_spread.as_radians();
+
+        let rocks = (0..self.count)
+            .map(|i| {
+                let a = rng.gen_range(self.inner.as_au()..=self.outer.as_au());
+                let radius = self.size.sample(rng.gen());
+
+                // tilt the mean plane normal by up to `spread` in a random direction
+                let tilt = rng.gen::<f64>() * spread;
+                let direction = rng.gen::<f64>() * std::f64::consts::TAU;
+                let n: Vec<f64> = (0..3)
+                    .map(|k| {
+                        normal[k] * tilt.cos()
+                            + (node_axis[k] * direction.cos() + in_plane[k] * direction.sin())
+                                * tilt.sin()
+                    })
+                    .collect();
+                let inclination = n[2].clamp(-1.0, 1.0).acos();
+                let longitude_of_ascending_node = if inclination.abs() < 1e-9 {
+                    0.0
+                } else {
+                    n[0].atan2(-n[1])
+                };
+
+                Rock {
+                    name: format!("{} {}", self.name, i + 1),
+                    radius,
+                    elements: OrbitalElements {
+                        semi_major_axis: a,
+                        eccentricity: rng.gen::<f64>() * self.max_eccentricity as f64,
+                        inclination,
+                        longitude_of_ascending_node,
+                        argument_of_periapsis: rng.gen_range(0.0..std::f64::consts::TAU),
+                        mean_anomaly_at_epoch: rng.gen_range(0.0..std::f64::consts::TAU),
+                        period: physical::orbital_period(Length::from_au(a), gm).as_days(),
+                    },
+                }
+            })
+            .collect();
+        Some(rocks)
+    }
+}
+
+/// orbit normal in the reference frame (x, y, z-north)
+fn plane_normal(inclination: f64, longitude_of_ascending_node: f64) -> [f64; 3] {
+    let (sin_o, cos_o) = longitude_of_ascending_node.sin_cos();
+    let (sin_i, cos_i) = inclination.sin_cos();
+    [sin_o * sin_i, -cos_o * sin_i, cos_i]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[test]
+fn test_belt() {
+    let saturn: Body = serde_yaml::from_str(
+        r#"
+name: saturn
+orbit: 9.5862
+orbit_time: 10759.22
+radius: 58232
+gm: 3.7931187e7
+axial_tilt: 26.73
+belts:
+  - name: rings
+    inner: 66900 km
+    outer: 140000 km
+    count: 500
+    size: { min: 1 m, max: 10 km }
+    inclination_spread: 0.01
+    equatorial: true
+    seed: 7
+"#,
+    )
+    .unwrap();
+    let belt = &saturn.belts[0];
+    let rocks = belt.rocks(&saturn).unwrap();
+    assert_eq!(rocks.len(), 500);
+    assert_eq!(rocks[41].name, "rings 42");
+    assert_eq!(
+        rocks[41].elements.semi_major_axis,
+        belt.rocks(&saturn).unwrap()[41].elements.semi_major_axis
+    );
+    let small = rocks
+        .iter()
+        .filter(|rock| rock.radius < Length::from_km(0.1))
+        .count();
+    for rock in rocks.iter() {
+        let a = Length::from_au(rock.elements.semi_major_axis);
+        assert!(a >= belt.inner && a <= belt.outer);
+        assert!(rock.radius >= Length::from_meters(1.0) && rock.radius <= Length::from_km(10.0));
+        // rings lie in saturn's equatorial plane
+        assert!((rock.elements.inclination.to_degrees() - 26.73).abs() < 0.02);
+        assert!((rock.elements.longitude_of_ascending_node.to_degrees() - 90.0).abs() < 0.1);
+    }
+    // most rocks are small
+    assert!(small > 400, "{}", small);
+
+    let massless = Body {
+        belts: saturn.belts.clone(),
+        ..Default::default()
+    };
+    assert!(belt.rocks(&massless).is_none());
+}
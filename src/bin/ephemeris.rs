@@ -0,0 +1,23 @@
+use universe::{ephemeris, system_file};
+
+// usage: ephemeris <system file> <body name> <days since epoch>
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        anyhow::bail!(
+            "usage: {} <system file> <body name> <days since epoch>",
+            args[0]
+        );
+    }
+    let root = system_file::load(std::path::Path::new(&args[1]))?;
+    let t: f64 = args[3].parse()?;
+
+    match ephemeris::state_at(&root, &args[2], t) {
+        Some(state) => {
+            println!("position: {:?}", state.position);
+            println!("velocity: {:?}", state.velocity);
+        }
+        None => anyhow::bail!("no body named '{}' in {}", args[2], args[1]),
+    }
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use universe::generator;
+
+// usage: generate <seed>
+// prints the generated system as YAML, e.g. for saving it as assets/<name>.system.yaml
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 {
+        anyhow::bail!("usage: {} <seed>", args[0]);
+    }
+    let seed: u64 = args[1].parse()?;
+    print!("{}", serde_yaml::to_string(&generator::generate(seed))?);
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use universe::csv_import::{self, ColumnMap};
+
+// usage: import_csv <table.csv> [<field>=<column> ...]
+// prints the imported system as YAML, e.g. for saving it as assets/<name>.system.yaml
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        anyhow::bail!("usage: {} <table.csv> [<field>=<column> ...]", args[0]);
+    }
+    let mut columns = ColumnMap::default();
+    for mapping in args[2..].iter() {
+        let (field, column) = mapping
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("expected <field>=<column>, got '{}'", mapping))?;
+        columns.set(field, column).map_err(anyhow::Error::msg)?;
+    }
+
+    let file = std::fs::File::open(&args[1])?;
+    match csv_import::import(file, &columns) {
+        Ok(root) => print!("{}", serde_yaml::to_string(&root)?),
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("{}: {}", args[1], error);
+            }
+            anyhow::bail!("import failed with {} error(s)", errors.len());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,420 @@
+//! Import bodies from orbital element tables (CSV), e.g. exported from the JPL small-body database.
+//!
+//! Every row is one body. The `parent` column names the body it orbits, exactly one row (the root)
+//! has an empty parent. Columns are mapped to `Body` fields by a `ColumnMap`, the defaults follow
+//! the JPL column names (`a`, `e`, `i`, `om`, `w`, `ma`, `per`, `GM`, `diameter`). Cells may be
+//! unit-annotated like in system files; bare numbers are in AU, days, degrees, km and kg. Missing
+//! orbital periods are derived from the parent's `GM` or `mass`.
+use std::{collections::HashMap, fmt, io::Read};
+
+use crate::{
+    quantity::{Angle, Duration, Length, Quantity},
+    units, Body, BodyKind,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    /// line in the CSV file, None for errors concerning the whole table
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// CSV column names for the `Body` fields
+#[derive(Debug, Clone)]
+pub struct ColumnMap {
+    pub name: String,
+    pub parent: String,
+    pub kind: String,
+    pub orbit: String,
+    pub orbit_time: String,
+    pub eccentricity: String,
+    pub inclination: String,
+    pub longitude_of_ascending_node: String,
+    pub argument_of_periapsis: String,
+    pub mean_anomaly_at_epoch: String,
+    pub radius: String,
+    /// only used if there is no radius column
+    pub diameter: String,
+    pub mass: String,
+    pub gm: String,
+    pub appearance: String,
+}
+
+impl Default for ColumnMap {
+    fn default() -> Self {
+        ColumnMap {
+            name: "name".into(),
+            parent: "parent".into(),
+            kind: "kind".into(),
+            orbit: "a".into(),
+            orbit_time: "per".into(),
+            eccentricity: "e".into(),
+            inclination: "i".into(),
+            longitude_of_ascending_node: "om".into(),
+            argument_of_periapsis: "w".into(),
+            mean_anomaly_at_epoch: "ma".into(),
+            radius: "radius".into(),
+            diameter: "diameter".into(),
+            mass: "mass".into(),
+            gm: "GM".into(),
+            appearance: "appearance".into(),
+        }
+    }
+}
+
+impl ColumnMap {
+    /// Map `field` (a `Body` field name, or `diameter`) to `column`
+    pub fn set(&mut self, field: &str, column: &str) -> Result<(), String> {
+        let target = match field {
+            "name" => &mut self.name,
+            "parent" => &mut self.parent,
+            "kind" => &mut self.kind,
+            "orbit" => &mut self.orbit,
+            "orbit_time" => &mut self.orbit_time,
+            "eccentricity" => &mut self.eccentricity,
+            "inclination" => &mut self.inclination,
+            "longitude_of_ascending_node" => &mut self.longitude_of_ascending_node,
+            "argument_of_periapsis" => &mut self.argument_of_periapsis,
+            "mean_anomaly_at_epoch" => &mut self.mean_anomaly_at_epoch,
+            "radius" => &mut self.radius,
+            "diameter" => &mut self.diameter,
+            "mass" => &mut self.mass,
+            "gm" => &mut self.gm,
+            "appearance" => &mut self.appearance,
+            _ => return Err(format!("unknown field '{}'", field)),
+        };
+        *target = column.to_string();
+        Ok(())
+    }
+}
+
+struct Row {
+    line: usize,
+    parent: String,
+    body: Body,
+}
+
+struct RowReader<'a> {
+    headers: HashMap<&'a str, usize>,
+    record: &'a csv::StringRecord,
+    line: usize,
+    errors: &'a mut Vec<ImportError>,
+}
+
+impl<'a> RowReader<'a> {
+    fn cell(&self, column: &str) -> Option<&'a str> {
+        let index = *self.headers.get(column)?;
+        let cell = self.record.get(index)?.trim();
+        if cell.is_empty() {
+            None
+        } else {
+            Some(cell)
+        }
+    }
+
+    fn quantity<Q: Quantity>(&mut self, column: &str, unit: &str) -> Option<Q> {
+        let cell = self.cell(column)?;
+        match units::parse_quantity(cell, unit) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push(ImportError {
+                    line: Some(self.line),
+                    message: format!("column '{}': {}", column, err),
+                });
+                None
+            }
+        }
+    }
+
+    fn number(&mut self, column: &str) -> Option<f64> {
+        let cell = self.cell(column)?;
+        match cell.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.errors.push(ImportError {
+                    line: Some(self.line),
+                    message: format!("column '{}': '{}' is not a number", column, cell),
+                });
+                None
+            }
+        }
+    }
+}
+
+fn read_row(
+    columns: &ColumnMap,
+    headers: HashMap<&str, usize>,
+    record: &csv::StringRecord,
+    errors: &mut Vec<ImportError>,
+) -> Option<Row> {
+    let line = record
+        .position()
+        .map_or(0, |position| position.line() as usize);
+    let mut row = RowReader {
+        headers,
+        record,
+        line,
+        errors,
+    };
+    let name = match row.cell(&columns.name) {
+        Some(name) => name.to_string(),
+        None => {
+            row.errors.push(ImportError {
+                line: Some(line),
+                message: "missing name".into(),
+            });
+            return None;
+        }
+    };
+    let kind = match row.cell(&columns.kind) {
+        Some(kind) => match serde_yaml::from_str::<BodyKind>(kind) {
+            Ok(kind) => kind,
+            Err(_) => {
+                row.errors.push(ImportError {
+                    line: Some(line),
+                    message: format!("unknown kind '{}'", kind),
+                });
+                BodyKind::default()
+            }
+        },
+        None => BodyKind::default(),
+    };
+    let radius = match row.quantity(&columns.radius, "km") {
+        Some(radius) => radius,
+        None => row
+            .quantity(&columns.diameter, "km")
+            .map_or(Length::ZERO, |diameter: Length| diameter / 2.0),
+    };
+
+    let body = Body {
+        name,
+        kind,
+        orbit: row.quantity(&columns.orbit, "AU").unwrap_or(Length::ZERO),
+        orbit_time: row
+            .quantity(&columns.orbit_time, "d")
+            .unwrap_or(Duration::ZERO),
+        eccentricity: row.number(&columns.eccentricity).unwrap_or(0.0) as f32,
+        inclination: row
+            .quantity(&columns.inclination, "deg")
+            .unwrap_or(Angle::ZERO),
+        longitude_of_ascending_node: row
+            .quantity(&columns.longitude_of_ascending_node, "deg")
+            .unwrap_or(Angle::ZERO),
+        argument_of_periapsis: row
+            .quantity(&columns.argument_of_periapsis, "deg")
+            .unwrap_or(Angle::ZERO),
+        mean_anomaly_at_epoch: row
+            .quantity(&columns.mean_anomaly_at_epoch, "deg")
+            .unwrap_or(Angle::ZERO),
+        radius,
+        mass: row.quantity(&columns.mass, "kg"),
+        gm: row.number(&columns.gm),
+        appearance: row.cell(&columns.appearance).unwrap_or_default().into(),
+        ..Default::default()
+    };
+    Some(Row {
+        line,
+        parent: row.cell(&columns.parent).unwrap_or_default().to_string(),
+        body,
+    })
+}
+
+/// attach the children of `body` (recursively), in file order
+fn build_tree(mut body: Body, children: &mut HashMap<String, Vec<Row>>) -> Body {
+    for row in children.remove(&body.name).unwrap_or_default() {
+        let mut satellite = build_tree(row.body, children);
+        if satellite.orbit_time <= Duration::ZERO {
+            if let Some(period) = satellite.kepler_period(&body) {
+                satellite.orbit_time = period;
+            }
+        }
+        body.satellites.push(satellite);
+    }
+    body
+}
+
+/// Read an orbital element table and build the `Body` tree. All problems (malformed cells, missing
+/// or unknown parents, ...) are collected and reported together.
+pub fn import<R: Read>(reader: R, columns: &ColumnMap) -> Result<Body, Vec<ImportError>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(reader);
+    let table_error = |message: String| {
+        vec![ImportError {
+            line: None,
+            message,
+        }]
+    };
+
+    let header_record = reader
+        .headers()
+        .map_err(|err| table_error(err.to_string()))?
+        .clone();
+    let headers: HashMap<&str, usize> = header_record
+        .iter()
+        .enumerate()
+        .map(|(i, header)| (header, i))
+        .collect();
+    for required in [&columns.name, &columns.parent] {
+        if !headers.contains_key(required.as_str()) {
+            return Err(table_error(format!("missing column '{}'", required)));
+        }
+    }
+
+    let mut errors = Vec::new();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        match record {
+            Ok(record) => rows.extend(read_row(columns, headers.clone(), &record, &mut errors)),
+            Err(err) => errors.push(ImportError {
+                line: err.position().map(|position| position.line() as usize),
+                message: err.to_string(),
+            }),
+        }
+    }
+
+    // duplicates are reported and skipped, they would share their children otherwise
+    let mut lines: HashMap<String, usize> = HashMap::new();
+    rows.retain(|row| match lines.get(&row.body.name) {
+        Some(first) => {
+            errors.push(ImportError {
+                line: Some(row.line),
+                message: format!(
+                    "duplicate name '{}' (first used on line {})",
+                    row.body.name, first
+                ),
+            });
+            false
+        }
+        None => {
+            lines.insert(row.body.name.clone(), row.line);
+            true
+        }
+    });
+    for row in rows.iter() {
+        if !row.parent.is_empty() && !lines.contains_key(&row.parent) {
+            errors.push(ImportError {
+                line: Some(row.line),
+                message: format!("parent '{}' of '{}' not found", row.parent, row.body.name),
+            });
+        }
+    }
+
+    let (roots, satellites): (Vec<Row>, Vec<Row>) =
+        rows.into_iter().partition(|row| row.parent.is_empty());
+    let mut children: HashMap<String, Vec<Row>> = HashMap::new();
+    for row in satellites {
+        children.entry(row.parent.clone()).or_default().push(row);
+    }
+    let mut roots = roots.into_iter();
+    let root = match (roots.next(), roots.next()) {
+        (Some(root), None) => Some(build_tree(root.body, &mut children)),
+        (None, _) => {
+            errors.push(ImportError {
+                line: None,
+                message: "no root body (a row with empty parent)".into(),
+            });
+            None
+        }
+        (Some(_), Some(second)) => {
+            errors.push(ImportError {
+                line: Some(second.line),
+                message: format!("second root body '{}'", second.body.name),
+            });
+            None
+        }
+    };
+    // whatever is left was not reachable from the root, i.e. parents that form a cycle
+    for row in children.values().flatten() {
+        if lines.contains_key(&row.parent) && root.is_some() {
+            errors.push(ImportError {
+                line: Some(row.line),
+                message: format!("'{}' is not connected to the root", row.body.name),
+            });
+        }
+    }
+
+    errors.sort_by_key(|error| error.line);
+    match root {
+        Some(root) if errors.is_empty() => Ok(root),
+        _ => Err(errors),
+    }
+}
+
+#[test]
+fn test_import() {
+    let table = "\
+# name, parent, semi-major axis, ...
+name,parent,kind,a,e,i,om,w,ma,per,GM,diameter
+sun,,star,,,,,,,,1.32712440018e11,1392700
+earth,sun,planet,1.0,0.0167,0.0,-11.26,114.2,358.6,365.256,398600.4,12742
+moon,earth,moon,384400 km,0.0549,5.145,125.08,318.15,135.27,27.32,,3474.8
+ceres,sun,asteroid,2.77,0.0785,10.59,80.3,73.6,77.4,,,939.4
+";
+    let sun = import(table.as_bytes(), &ColumnMap::default()).unwrap();
+    assert_eq!(sun.kind, BodyKind::Star);
+    assert_eq!(sun.radius, Length::from_km(696350.0));
+    assert_eq!(sun.gm, Some(1.32712440018e11));
+    let names: Vec<_> = sun
+        .iter_tree()
+        .iter()
+        .map(|body| body.name.as_str())
+        .collect();
+    assert_eq!(names, ["sun", "earth", "moon", "ceres"]);
+    let moon = &sun.satellites[0].satellites[0];
+    assert!((moon.orbit.as_km() - 384400.0).abs() < 1e-6);
+    assert_eq!(moon.inclination, Angle::from_degrees(5.145));
+    // derived from the sun's GM
+    let ceres = &sun.satellites[1];
+    assert!(
+        (ceres.orbit_time.as_days() - 1680.0).abs() < 5.0,
+        "{}",
+        ceres.orbit_time
+    );
+
+    let broken = "\
+name,parent,a,per
+sun,,,
+earth,sun,1.0,365
+moon,terra,0.00257,27
+phobos,mars,1e-4,0.3
+";
+    let errors = import(broken.as_bytes(), &ColumnMap::default()).unwrap_err();
+    let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "line 4: parent 'terra' of 'moon' not found",
+            "line 5: parent 'mars' of 'phobos' not found"
+        ]
+    );
+    let duplicate = "name,parent\nsun,\nearth,sun\nearth,sun\nmoon,earth\nearth,sun\n";
+    let errors = import(duplicate.as_bytes(), &ColumnMap::default()).unwrap_err();
+    let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "line 4: duplicate name 'earth' (first used on line 3)",
+            "line 6: duplicate name 'earth' (first used on line 3)"
+        ]
+    );
+
+    let mut columns = ColumnMap::default();
+    columns.set("orbit", "semi_major_axis").unwrap();
+    assert!(columns.set("orbits", "a").is_err());
+    let remapped = "name,parent,semi_major_axis,per\nsun,,,\nearth,sun,1.0,365\n";
+    let sun = import(remapped.as_bytes(), &columns).unwrap();
+    assert_eq!(sun.satellites[0].orbit, Length::from_au(1.0));
+}
@@ -0,0 +1,104 @@
+//! Analytic ephemeris: body positions computed directly from the `Body` tree, without any ECS state.
+use bevy::math::DVec3;
+
+use crate::{orbit::OrbitalElements, Body};
+
+/// Position (unit of `Body::orbit`) and velocity (unit of `Body::orbit` per day).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StateVector {
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+impl std::ops::Add for StateVector {
+    type Output = StateVector;
+
+    fn add(self, rhs: StateVector) -> StateVector {
+        StateVector {
+            position: self.position + rhs.position,
+            velocity: self.velocity + rhs.velocity,
+        }
+    }
+}
+
+/// State relative to the parent body at time `t` (days since epoch).
+pub fn local_state(elements: &OrbitalElements, t: f64) -> StateVector {
+    StateVector {
+        position: elements.position(t),
+        velocity: elements.velocity(t),
+    }
+}
+
+/// Chain of bodies from `root` down to the body called `name` (both inclusive).
+pub fn find_path<'a>(root: &'a Body, name: &str) -> Option<Vec<&'a Body>> {
+    if root.name == name {
+        return Some(vec![root]);
+    }
+    root.satellites.iter().find_map(|satellite| {
+        let mut path = find_path(satellite, name)?;
+        path.insert(0, root);
+        Some(path)
+    })
+}
+
+/// State of body `name` in the frame of `root` at time `t` (days since epoch). The root body is
+/// fixed at the origin, its own orbit is ignored (this matches how the hierarchy is spawned).
+pub fn state_at(root: &Body, name: &str, t: f64) -> Option<StateVector> {
+    let path = find_path(root, name)?;
+    Some(
+        path.iter()
+            .skip(1)
+            .map(|body| local_state(&body.orbital_elements(), t))
+            .fold(StateVector::default(), |acc, state| acc + state),
+    )
+}
+
+#[test]
+fn test_state_at() {
+    let sun: Body = serde_yaml::from_str(
+        r#"
+name: sun
+orbit: 0.0
+orbit_time: 0.0
+day: 0.0
+radius: 1400000.0
+appearance: none
+satellites:
+  - name: earth
+    orbit: 1.0
+    orbit_time: 365.25
+    eccentricity: 0.0167
+    day: 1.0
+    radius: 6100.0
+    appearance: none
+    satellites:
+      - name: moon
+        orbit: 0.00257
+        orbit_time: 27.32
+        eccentricity: 0.0549
+        inclination: 5.145
+        day: 27.32
+        radius: 1700.0
+        appearance: none
+        satellites: []
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(state_at(&sun, "sun", 1234.0), Some(StateVector::default()));
+    assert_eq!(state_at(&sun, "pluto", 1234.0), None);
+
+    let t = 1234.0;
+    let earth = state_at(&sun, "earth", t).unwrap();
+    let moon = state_at(&sun, "moon", t).unwrap();
+    let moon_local = local_state(&sun.satellites[0].satellites[0].orbital_elements(), t);
+    assert!((moon.position - earth.position - moon_local.position).length() < 1e-12);
+    assert!((earth.position.length() - 1.0).abs() < 0.02);
+
+    // analytic velocity must match the numeric derivative of the position
+    let dt = 1e-4;
+    let numeric = (state_at(&sun, "moon", t + dt).unwrap().position
+        - state_at(&sun, "moon", t - dt).unwrap().position)
+        / (2.0 * dt);
+    assert!((numeric - moon.velocity).length() < 1e-8);
+}
@@ -0,0 +1,78 @@
+//! Floating origin.
+//!
+//! f32 transforms lose precision quickly at AU scale (1 km is ~6.7e-9 units). The render origin is
+//! therefore kept near the ship: whenever the ship strays farther than `REBASE_DISTANCE` from it, the
+//! origin is moved to the ship. The true position of the render origin is tracked in f64, see
+//! `universal_position` for how render transforms are derived from it.
+use bevy::{math::DVec3, prelude::*};
+
+use crate::{consts::KM_TO_UNIT, ship::Ship};
+
+/// distance from the render origin (in units) that triggers a rebase
+pub const REBASE_DISTANCE: f32 = 10_000.0 * KM_TO_UNIT;
+
+#[derive(Default, Debug)]
+pub struct FloatingOrigin {
+    /// position of the render origin, relative to the root of the star system. Outside of the
+    /// realistic scale mode this is a position in the displayed system.
+    pub offset: DVec3,
+}
+
+impl FloatingOrigin {
+    pub fn true_position(&self, render: Vec3) -> DVec3 {
+        self.offset + render.as_dvec3()
+    }
+
+    pub fn render_position(&self, true_position: DVec3) -> Vec3 {
+        (true_position - self.offset).as_vec3()
+    }
+
+    /// Move the origin by `shift` (in render coordinates). Everything positioned in render
+    /// coordinates has to be moved by `-shift`.
+    pub fn rebase(&mut self, shift: Vec3) {
+        self.offset += shift.as_dvec3();
+    }
+}
+
+// Moves the origin to the ship. Bodies follow through their `UniversalPosition`. Heron picks up the
+// changed transforms of the ships and keeps their velocities.
+pub fn rebase_system(
+    mut origin: ResMut<FloatingOrigin>,
+    mut query: Query<&mut Transform, With<Ship>>,
+) {
+    let shift = match query.iter().next() {
+        Some(transform) if transform.translation.length() > REBASE_DISTANCE => {
+            transform.translation
+        }
+        _ => return,
+    };
+    origin.rebase(shift);
+    for mut transform in query.iter_mut() {
+        transform.translation -= shift;
+    }
+    debug!("rebased origin to {:?}", origin.offset);
+}
+
+#[derive(Default)]
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingOrigin>()
+            .add_system(rebase_system.system());
+    }
+}
+
+#[test]
+fn test_rebase() {
+    let mut origin = FloatingOrigin::default();
+    // a ship near neptune
+    let ship = DVec3::new(30.0, 0.0, 1e-9);
+    let render = origin.render_position(ship);
+    origin.rebase(render);
+    assert!(origin.render_position(ship).length() < 1e-12);
+    // small offsets survive far from the root
+    let neighbour = ship + DVec3::new(0.0, 0.0, 1e-9);
+    assert!((origin.render_position(neighbour).z - 1e-9).abs() < 1e-15);
+    assert_eq!(origin.true_position(Vec3::ZERO), origin.offset);
+}
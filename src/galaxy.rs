@@ -0,0 +1,159 @@
+//! Universe descriptions: several star systems at interstellar distances.
+//!
+//! Only one system is spawned in full detail at a time. The others are rendered as distant stars
+//! and can be reached by interstellar travel.
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    math::DVec3,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+fn default_luminosity() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SystemEntry {
+    pub name: String,
+    /// galactic coordinates in light years (bevy axes, y towards galactic north)
+    pub position: [f64; 3],
+    /// system file, relative to the asset folder
+    #[serde(default)]
+    pub file: Option<String>,
+    /// seed for the procedural generator, alternative to `file`
+    #[serde(default)]
+    pub generate: Option<u64>,
+    /// in solar luminosities
+    #[serde(default = "default_luminosity")]
+    pub luminosity: f32,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+}
+
+impl SystemEntry {
+    pub fn position(&self) -> DVec3 {
+        DVec3::from(self.position)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TypeUuid)]
+#[uuid = "b1d3c5e8-7f2a-4c61-8d9e-2a4b6c8e0f13"]
+pub struct Universe {
+    /// name of the system the game starts in
+    pub start: String,
+    pub systems: Vec<SystemEntry>,
+}
+
+impl Universe {
+    pub fn find(&self, name: &str) -> Option<&SystemEntry> {
+        self.systems.iter().find(|system| system.name == name)
+    }
+
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.find(&self.start).is_none() {
+            anyhow::bail!("start system '{}' does not exist", self.start);
+        }
+        let mut names = std::collections::HashSet::new();
+        for system in self.systems.iter() {
+            if !names.insert(system.name.as_str()) {
+                anyhow::bail!("duplicate system name '{}'", system.name);
+            }
+            if system.file.is_some() == system.generate.is_some() {
+                anyhow::bail!(
+                    "system '{}' needs exactly one of 'file' and 'generate'",
+                    system.name
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Brightness relative to the sun seen from 1 light year (inverse-square falloff, clamped near 0)
+pub fn apparent_brightness(luminosity: f32, distance_ly: f64) -> f32 {
+    luminosity / (distance_ly.max(0.1) * distance_ly.max(0.1)) as f32
+}
+
+/// Interpolated galactic position while travelling from `from` to `to`, `progress` in [0, 1].
+/// Eased, so departure and arrival are slow.
+pub fn travel_position(from: DVec3, to: DVec3, progress: f32) -> DVec3 {
+    let t = progress.clamp(0.0, 1.0) as f64;
+    let eased = t * t * (3.0 - 2.0 * t);
+    from.lerp(to, eased)
+}
+
+/// Loads universe descriptions (`*.universe.yaml`)
+#[derive(Default)]
+pub struct UniverseLoader;
+
+impl AssetLoader for UniverseLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let universe: Universe = serde_yaml::from_slice(bytes)?;
+            universe.check()?;
+            load_context.set_default_asset(LoadedAsset::new(universe));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["universe.yaml"]
+    }
+}
+
+#[derive(Default)]
+pub struct GalaxyPlugin;
+
+impl Plugin for GalaxyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Universe>()
+            .init_asset_loader::<UniverseLoader>();
+    }
+}
+
+#[test]
+fn test_universe() {
+    let universe: Universe = serde_yaml::from_str(
+        r#"
+start: sol
+systems:
+  - { name: sol, position: [0, 0, 0], file: sol.system.yaml }
+  - { name: sirius, position: [-1.61, 8.06, -2.47], generate: 6, luminosity: 25.4 }
+"#,
+    )
+    .unwrap();
+    universe.check().unwrap();
+    let sirius = universe.find("sirius").unwrap();
+    let distance = sirius.position().length();
+    assert!((distance - 8.6).abs() < 0.1);
+    assert!((apparent_brightness(sirius.luminosity, distance) - 0.34).abs() < 0.01);
+
+    let from = universe.find("sol").unwrap().position();
+    assert_eq!(travel_position(from, sirius.position(), 0.0), from);
+    assert_eq!(
+        travel_position(from, sirius.position(), 1.0),
+        sirius.position()
+    );
+
+    let broken: Universe =
+        serde_yaml::from_str("start: nowhere\nsystems:\n  - { name: sol, position: [0, 0, 0] }\n")
+            .unwrap();
+    assert!(broken.check().is_err());
+    let duplicate: Universe = serde_yaml::from_str(
+        r#"
+start: sol
+systems:
+  - { name: sol, position: [0, 0, 0], file: sol.system.yaml }
+  - { name: sol, position: [1, 0, 0], generate: 1 }
+"#,
+    )
+    .unwrap();
+    assert!(duplicate.check().is_err());
+}
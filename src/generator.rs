@@ -0,0 +1,224 @@
+//! Procedural star systems.
+//!
+//! Loosely physical: planets follow a Titius-Bode-like spacing, rocky planets form inside the snow
+//! line and gas giants outside, an asteroid belt may take the place of a planet near the snow line,
+//! moons stay well inside their planet's Hill sphere and orbital periods follow Kepler's third law.
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    belt::{Belt, SizeDistribution},
+    quantity::{Angle, Duration, Length, Mass},
+    Body, BodyKind,
+};
+
+const SUN_RADIUS_KM: f64 = 696_000.0;
+const EARTH_RADIUS_KM: f64 = 6_371.0;
+/// earth mass in solar masses
+const EARTH_MASS: f64 = 3.003e-6;
+const SUN_MASS_KG: f64 = 1.988_47e30;
+const DAYS_PER_YEAR: f64 = 365.25;
+
+const ROCKY_APPEARANCES: [&str; 4] = [
+    "mercury_uv01/mercury_uv01.gltf",
+    "venus_uv01/venus_uv01.gltf",
+    "earth_uv01/earth_uv01.gltf",
+    "mars_uv01/mars_uv01.gltf",
+];
+const GAS_GIANT_APPEARANCES: [&str; 4] = [
+    "jupiter_uv01/jupiter_uv01.gltf",
+    "saturn_uv01/saturn_uv01.gltf",
+    "uranus_uv01/uranus_uv01.gltf",
+    "neptune_uv01/neptune_uv01.gltf",
+];
+const MOON_APPEARANCE: &str = "moon_uv01/moon_uv01.gltf";
+const STAR_APPEARANCE: &str = "sun_uv01/sun_uv01.gltf";
+
+const ROMAN: [&str; 10] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X"];
+
+/// orbital period for semi-major axis `a` around a central mass (solar masses)
+fn kepler_period(a: Length, central_mass: f64) -> Duration {
+    let a = a.as_au();
+    Duration::from_days(DAYS_PER_YEAR * (a * a * a / central_mass).sqrt())
+}
+
+fn degrees(rng: &mut ChaCha8Rng, range: std::ops::Range<f64>) -> Angle {
+    Angle::from_degrees(rng.gen_range(range))
+}
+
+struct Generator {
+    rng: ChaCha8Rng,
+}
+
+impl Generator {
+    fn orbit(&mut self, body: &mut Body, a: Length, central_mass: f64, max_eccentricity: f32) {
+        body.orbit = a;
+        body.orbit_time = kepler_period(a, central_mass);
+        body.eccentricity = self.rng.gen::<f32>() * max_eccentricity;
+        body.inclination = degrees(&mut self.rng, 0.0..4.0);
+        body.longitude_of_ascending_node = degrees(&mut self.rng, 0.0..360.0);
+        body.argument_of_periapsis = degrees(&mut self.rng, 0.0..360.0);
+        body.mean_anomaly_at_epoch = degrees(&mut self.rng, 0.0..360.0);
+    }
+
+    fn moons(&mut self, planet: &mut Body, planet_mass: f64, star_mass: f64) {
+        let hill_radius = planet.orbit * (planet_mass / (3.0 * star_mass)).cbrt();
+        // stable prograde moons stay within roughly half the hill sphere
+        let outer = hill_radius * 0.4;
+        let inner = planet.radius * 3.0;
+        if outer <= inner * 2.0 {
+            return;
+        }
+        let max_moons = if planet.radius.as_km() > 3.0 * EARTH_RADIUS_KM {
+            6
+        } else {
+            2
+        };
+        let count = self.rng.gen_range(0..=max_moons);
+        let mut moon_a = inner * self.rng.gen_range(1.0..2.0);
+        for i in 0..count {
+            if moon_a > outer {
+                break;
+            }
+            let mut moon = Body {
+                name: format!("{} {}", planet.name, ROMAN[i]),
+                kind: BodyKind::Moon,
+                radius: Length::from_km(
+                    self.rng
+                        .gen_range(200.0..(planet.radius.as_km() * 0.3).max(300.0)),
+                ),
+                appearance: MOON_APPEARANCE.into(),
+                ..Default::default()
+            };
+            self.orbit(&mut moon, moon_a, planet_mass, 0.05);
+            // tidally locked
+            moon.day = moon.orbit_time;
+            planet.satellites.push(moon);
+            moon_a *= self.rng.gen_range(1.4..2.2);
+        }
+    }
+
+    fn belt(&mut self, star: &mut Body, name: &str, a: Length) {
+        star.belts.push(Belt {
+            name: name.to_string(),
+            inner: a * self.rng.gen_range(0.8..0.9),
+            outer: a * self.rng.gen_range(1.1..1.3),
+            count: self.rng.gen_range(200..800),
+            size: SizeDistribution {
+                min: Length::from_km(1.0),
+                max: Length::from_km(self.rng.gen_range(100.0..500.0)),
+                exponent: 3.5,
+            },
+            inclination_spread: degrees(&mut self.rng, 5.0..20.0),
+            max_eccentricity: 0.2,
+            equatorial: false,
+            seed: self.rng.gen(),
+            appearance: String::new(),
+        });
+    }
+
+    fn system(&mut self, name: &str) -> Body {
+        let star_mass: f64 = self.rng.gen_range(0.5..1.5);
+        let luminosity = star_mass.powf(3.5);
+        let snow_line = 2.7 * luminosity.sqrt();
+
+        let mut star = Body {
+            name: name.to_string(),
+            kind: BodyKind::Star,
+            radius: Length::from_km(SUN_RADIUS_KM * star_mass.powf(0.8)),
+            mass: Some(Mass::from_kg(star_mass * SUN_MASS_KG)),
+            day: Duration::from_days(self.rng.gen_range(20.0..35.0)),
+            appearance: STAR_APPEARANCE.into(),
+            ..Default::default()
+        };
+
+        // titius-bode: a_n = a_0 + k * 2^n
+        let a0 = self.rng.gen_range(0.2..0.5) * luminosity.sqrt();
+        let k = self.rng.gen_range(0.15..0.4) * luminosity.sqrt();
+        let planet_count = self.rng.gen_range(3..=9);
+        let mut belt_placed = false;
+        let mut planet_index = 0;
+        for n in 0..planet_count {
+            let a = Length::from_au((a0 + k * 2f64.powi(n)) * self.rng.gen_range(0.9..1.1));
+
+            // replace one planet near the snow line by an asteroid belt
+            let snow_line_ratio = a.as_au() / snow_line;
+            if !belt_placed && snow_line_ratio > 0.8 && snow_line_ratio < 1.5 {
+                belt_placed = true;
+                self.belt(&mut star, &format!("{} belt", name), a);
+                continue;
+            }
+
+            let gas_giant = a.as_au() > snow_line;
+            let (radius, density) = if gas_giant {
+                (self.rng.gen_range(3.5..12.0) * EARTH_RADIUS_KM, 0.25)
+            } else {
+                (self.rng.gen_range(0.3..1.8) * EARTH_RADIUS_KM, 1.0)
+            };
+            let appearance = if gas_giant {
+                GAS_GIANT_APPEARANCES[self.rng.gen_range(0..GAS_GIANT_APPEARANCES.len())]
+            } else {
+                ROCKY_APPEARANCES[self.rng.gen_range(0..ROCKY_APPEARANCES.len())]
+            };
+            // mass from radius, relative to earth density
+            let planet_mass = EARTH_MASS * density * (radius / EARTH_RADIUS_KM).powi(3);
+
+            let mut planet = Body {
+                name: format!("{} {}", name, (b'b' + planet_index) as char),
+                kind: BodyKind::Planet,
+                radius: Length::from_km(radius),
+                mass: Some(Mass::from_kg(planet_mass * SUN_MASS_KG)),
+                day: Duration::from_days(self.rng.gen_range(0.3..3.0)),
+                axial_tilt: degrees(&mut self.rng, 0.0..30.0),
+                albedo: Some(if gas_giant { 0.5 } else { 0.3 }),
+                appearance: appearance.into(),
+                ..Default::default()
+            };
+            planet_index += 1;
+            self.orbit(&mut planet, a, star_mass, 0.1);
+            self.moons(&mut planet, planet_mass, star_mass);
+
+            // the ship starts in a low orbit around the innermost planet
+            if planet_index == 1 {
+                let mut spawn_point = Body {
+                    name: "ship".into(),
+                    kind: BodyKind::SpawnPoint,
+                    ..Default::default()
+                };
+                let a = Length::from_km(radius * 2.0);
+                self.orbit(&mut spawn_point, a, planet_mass, 0.0);
+                planet.satellites.insert(0, spawn_point);
+            }
+            star.satellites.push(planet);
+        }
+        star
+    }
+}
+
+/// Generate a star system. The same seed always produces the same system.
+pub fn generate(seed: u64) -> Body {
+    let mut generator = Generator {
+        rng: ChaCha8Rng::seed_from_u64(seed),
+    };
+    generator.system(&format!("gen-{}", seed))
+}
+
+#[test]
+fn test_generate() {
+    for seed in 0..32 {
+        let system = generate(seed);
+        assert_eq!(
+            serde_yaml::to_string(&system).unwrap(),
+            serde_yaml::to_string(&generate(seed)).unwrap()
+        );
+        assert_eq!(system.kind, BodyKind::Star);
+        assert!(system.satellites.len() >= 3);
+
+        // the models are not part of the repository, which is only a warning
+        let errors: Vec<_> = crate::validate::validate(&system, std::path::Path::new("assets"))
+            .into_iter()
+            .filter(|error| error.is_error())
+            .collect();
+        assert!(errors.is_empty(), "seed {}: {:?}", seed, errors);
+    }
+}
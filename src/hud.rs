@@ -5,6 +5,8 @@ use bevy::{
     prelude::*,
 };
 
+use crate::property::PropertyValue;
+
 // mod button;
 
 // // FIXME: only defined here because hud code directly modifies it. Implementation should be moved from main.rs
@@ -47,6 +49,7 @@ pub enum HudSrc {
 pub enum HudElement {
     TextWithSource(HudSrc),
     ToggleButtonProperty(String, String, String),
+    SelectProperty(String, Vec<(String, PropertyValue)>),
     EditThis,
 }
 
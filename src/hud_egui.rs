@@ -11,9 +11,12 @@ use bevy_egui::{
 
 use crate::{
     hud::{HudElement, HudPlotDiagnostic, HudSrc, RenderStatus},
+    loading::LoadingStatus,
     property::{
         self, PropertyAccess, PropertyName, PropertyRegistry, PropertyUpdateEvent, PropertyValue,
     },
+    scale::ScaleMode,
+    sim_clock,
 };
 
 fn mag_to_str(mag: i32) -> &'static str {
@@ -30,6 +33,11 @@ fn mag_to_str(mag: i32) -> &'static str {
 }
 
 pub fn hud_egui_setup_system(mut commands: Commands, mut hud_order: ResMut<HudOrder>) {
+    commands
+        .spawn()
+        .insert(HudElement::TextWithSource(HudSrc::LoadingScreen))
+        .insert(hud_order.next().in_group("0. Loading"));
+
     let hud_group = "1. Diag";
     commands
         .spawn()
@@ -56,6 +64,82 @@ pub fn hud_egui_setup_system(mut commands: Commands, mut hud_order: ResMut<HudOr
         .insert(property::PropertyAccess::default())
         .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
         .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(property::PropertyName("ship.reference".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(property::PropertyName("ship.target".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(property::PropertyName("system.summary".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(property::PropertyName("system.errors".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+
+    let hud_group = "2. Sim";
+    commands
+        .spawn()
+        .insert(property::PropertyName("sim.time".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(HudElement::ToggleButtonProperty(
+            "sim.paused".into(),
+            "paused".into(),
+            "running".into(),
+        ))
+        .insert(hud_order.next().in_group(hud_group));
+    commands
+        .spawn()
+        .insert(HudElement::SelectProperty(
+            "sim.warp".into(),
+            sim_clock::WARP_STEPS
+                .iter()
+                .map(|warp| (format!("{}x", warp), PropertyValue::Float(*warp as f32)))
+                .collect(),
+        ))
+        .insert(hud_order.next().in_group(hud_group));
+
+    // the destination list is added once the universe is loaded
+    let hud_group = "3. Travel";
+    commands
+        .spawn()
+        .insert(property::PropertyName("universe.system".into()))
+        .insert(property::PropertyAccess::default())
+        .insert(HudElement::TextWithSource(HudSrc::PropertyAccess))
+        .insert(hud_order.next().in_group(hud_group));
+
+    let hud_group = "4. View";
+    commands
+        .spawn()
+        .insert(HudElement::SelectProperty(
+            "view.scale".into(),
+            ScaleMode::ALL
+                .iter()
+                .map(|mode| {
+                    (
+                        mode.name().into(),
+                        PropertyValue::String(mode.name().into()),
+                    )
+                })
+                .collect(),
+        ))
+        .insert(hud_order.next().in_group(hud_group));
 
     // commands
     //     .spawn()
@@ -80,6 +164,7 @@ pub fn hud_egui_system(
     property_query: Query<(&PropertyValue, &PropertyName)>,
     diagnostics: Res<Diagnostics>,
     render_status: Res<RenderStatus>,
+    loading_status: Res<LoadingStatus>,
     hud_elements_query: Query<(Entity, &HudOrder, &HudElement)>,
     mut string_edit_query: Query<&mut StringEdit>,
     mut hud_plot_diagnostic: Query<&mut HudPlotDiagnostic>,
@@ -121,7 +206,7 @@ pub fn hud_egui_system(
                             HudSrc::RenderStatus => {
                                 format!("render status: {}", render_status.text)
                             }
-                            HudSrc::LoadingScreen => String::new(),
+                            HudSrc::LoadingScreen => loading_status.text(),
                             HudSrc::PropertName(property_name) => {
                                 match property_registry.get(property_name) {
                                     Some(rs) => {
@@ -146,6 +231,9 @@ pub fn hud_egui_system(
                                     PropertyValue::Float(f) => {
                                         format!("{:.3}", f)
                                     }
+                                    PropertyValue::Length(length) => length.to_string(),
+                                    PropertyValue::Velocity(velocity) => velocity.to_string(),
+                                    PropertyValue::Duration(duration) => duration.to_string(),
                                     _ => "unsupported type".to_string(),
                                 },
                                 _ => {
@@ -175,6 +263,26 @@ pub fn hud_egui_system(
                             }
                         }
                     }
+                    HudElement::SelectProperty(property_name, choices) => {
+                        match property_registry.get(property_name) {
+                            Some(rs) => {
+                                let (current, _) = property_query.get(rs).unwrap();
+                                ui.horizontal(|ui| {
+                                    for (text, value) in choices {
+                                        if ui.selectable_label(current == value, text).clicked() {
+                                            property_update_events.send(PropertyUpdateEvent::new(
+                                                property_name.clone(),
+                                                value.clone(),
+                                            ));
+                                        }
+                                    }
+                                });
+                            }
+                            _ => {
+                                ui.label(format!("failed: {}", property_name));
+                            }
+                        }
+                    }
                     HudElement::EditThis => match property_query.get(entity) {
                         Ok((property_value, property_name)) => {
                             match property_value {
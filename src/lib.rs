@@ -1,11 +1,37 @@
 #![feature(slice_group_by)]
 
+use appearance::Appearance;
+use bevy::reflect::TypeUuid;
+use quantity::{Angle, Duration, Length, Mass};
 use serde::{Deserialize, Serialize};
 
+pub mod appearance;
+pub mod belt;
+pub mod csv_import;
+pub mod ephemeris;
+pub mod floating_origin;
+pub mod galaxy;
+pub mod generator;
 pub mod hud;
 pub mod hud_egui;
+pub mod lighting;
+pub mod loading;
+pub mod lod;
+pub mod orbit;
+pub mod physical;
+pub mod procedural;
 pub mod property;
+pub mod quantity;
+pub mod save_game;
+pub mod scale;
 pub mod ship;
+pub mod sim_clock;
+pub mod soi;
+pub mod system_asset;
+pub mod system_file;
+pub mod units;
+pub mod universal_position;
+pub mod validate;
 
 pub mod prelude {
     pub use super::consts::*;
@@ -14,13 +40,11 @@ pub mod prelude {
 
 pub mod consts {
     pub const KILOMETER: f32 = 1e3;
-    pub const AU: f32 = 1.495_978_6e11;
+    pub const AU: f32 = (crate::quantity::KM_PER_AU as f32) * KILOMETER;
     // pub const AU_TO_UNIT: f32 = AU / 100.0;
     // pub const KM_TO_UNIT: f32 = KILOMETER / 100.0;
     pub const AU_TO_UNIT: f32 = 1.0;
     pub const KM_TO_UNIT: f32 = KILOMETER / AU;
-    pub const RADIUS_BOOST: f32 = 1e0;
-    pub const ORBIT_MUL: f32 = 1e-0;
     // pub const RADIUS_SUN: f32 = 1400000.0 * KILOMETER;
     // pub const ORBIT_EARTH: f32 = 14000000.0 * KILOMETER;
     // pub const RADIUS_EARTH: f32 = 6100.0 * KILOMETER * 1.0;
@@ -28,44 +52,229 @@ pub mod consts {
     // pub const ORBIT_MOON: f32 = 370000.0 * KILOMETER;
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyKind {
+    Star,
+    Planet,
+    Moon,
+    Asteroid,
+    Station,
+    /// invisible location where ships can be spawned
+    SpawnPoint,
+    /// invisible center of mass of a multi-body system
+    Barycenter,
+}
+
+impl Default for BodyKind {
+    fn default() -> Self {
+        BodyKind::Planet
+    }
+}
+
+impl BodyKind {
+    pub const ALL: [BodyKind; 7] = [
+        BodyKind::Star,
+        BodyKind::Planet,
+        BodyKind::Moon,
+        BodyKind::Asteroid,
+        BodyKind::Station,
+        BodyKind::SpawnPoint,
+        BodyKind::Barycenter,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyKind::Star => "star",
+            BodyKind::Planet => "planet",
+            BodyKind::Moon => "moon",
+            BodyKind::Asteroid => "asteroid",
+            BodyKind::Station => "station",
+            BodyKind::SpawnPoint => "spawn point",
+            BodyKind::Barycenter => "barycenter",
+        }
+    }
+
+    /// kinds without a visual representation
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, BodyKind::SpawnPoint | BodyKind::Barycenter)
+    }
+}
+
+/// A body and its satellites, as described in a system file.
+///
+/// Quantities may be unit-annotated strings (see `units`), bare numbers are in the canonical unit of
+/// each field: AU for `orbit`, km for `radius`, days for `orbit_time` and `day`, degrees for angles.
+/// In memory they are typed (see `quantity`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, TypeUuid)]
+#[uuid = "6f2d5a84-1c5e-4a7b-9e0b-3f1d2c8b7a41"]
 pub struct Body {
     pub name: String,
-    pub orbit: f32,
-    pub orbit_time: f32,
-    pub day: f32,
+    /// a planet unless given, except for the root of a system file, see `system_file`
+    #[serde(default)]
+    pub kind: BodyKind,
+    /// the ship starts at this spawn point, see `Body::start_spawn_point`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub start: bool,
+    /// semi-major axis
+    #[serde(with = "units::au")]
+    pub orbit: Length,
+    #[serde(with = "units::days")]
+    pub orbit_time: Duration,
+    // remaining orbital elements. Default to a circular equatorial orbit.
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default, with = "units::degrees")]
+    pub inclination: Angle,
+    #[serde(default, with = "units::degrees")]
+    pub longitude_of_ascending_node: Angle,
+    #[serde(default, with = "units::degrees")]
+    pub argument_of_periapsis: Angle,
+    #[serde(default, with = "units::degrees")]
+    pub mean_anomaly_at_epoch: Angle,
+    #[serde(default, with = "units::days")]
+    pub day: Duration,
+    #[serde(default)]
     pub satellites: Vec<Body>,
-    pub radius: f32,
-    pub appearance: String,
+    /// asteroid belts and rings around this body
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub belts: Vec<belt::Belt>,
+    #[serde(default, with = "units::km")]
+    pub radius: Length,
+    /// a model path or a full appearance, see `appearance`
+    #[serde(default, with = "appearance::path_or_spec")]
+    pub appearance: Appearance,
+
+    // optional physical properties, see `physical` for derived quantities
+    #[serde(
+        default,
+        with = "units::optional_kg",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mass: Option<Mass>,
+    /// standard gravitational parameter in km^3/s^2, takes precedence over `mass`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gm: Option<f64>,
+    #[serde(default, with = "units::degrees")]
+    pub axial_tilt: Angle,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub albedo: Option<f32>,
+    #[serde(
+        default,
+        with = "units::optional_km",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub atmosphere_height: Option<Length>,
+    /// sidereal rotation period, takes precedence over `day` (the solar day) for spin
+    #[serde(
+        default,
+        with = "units::optional_days",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rotation_period: Option<Duration>,
+}
+
+impl Body {
+    /// this body and all its (direct and indirect) satellites, depth-first
+    pub fn iter_tree(&self) -> Vec<&Body> {
+        let mut bodies = vec![self];
+        for satellite in self.satellites.iter() {
+            bodies.extend(satellite.iter_tree());
+        }
+        bodies
+    }
+
+    /// Where the ship starts: the spawn point marked `start`, otherwise the first one
+    pub fn start_spawn_point(&self) -> Option<&Body> {
+        let spawn_points: Vec<_> = self
+            .iter_tree()
+            .into_iter()
+            .filter(|body| body.kind == BodyKind::SpawnPoint)
+            .collect();
+        spawn_points
+            .iter()
+            .find(|body| body.start)
+            .or_else(|| spawn_points.first())
+            .copied()
+    }
+
+    pub fn orbital_elements(&self) -> orbit::OrbitalElements {
+        orbit::OrbitalElements {
+            semi_major_axis: self.orbit.as_au(),
+            eccentricity: self.eccentricity as f64,
+            inclination: self.inclination.as_radians(),
+            longitude_of_ascending_node: self.longitude_of_ascending_node.as_radians(),
+            argument_of_periapsis: self.argument_of_periapsis.as_radians(),
+            mean_anomaly_at_epoch: self.mean_anomaly_at_epoch.as_radians(),
+            period: self.orbit_time.as_days(),
+        }
+    }
 }
 
 #[test]
 fn test_body() {
     let sun = Body {
         name: "sun".to_string(),
-        orbit: 0.0,
-        orbit_time: 0.0,
-        day: -365.0,
-        radius: 1.4e6,
+        day: Duration::from_days(-365.0),
+        radius: Length::from_km(1.4e6),
         appearance: "earth_gltf02/earth.gltf".into(),
         satellites: vec![Body {
             name: "earth".into(),
-            orbit: 14e6,
-            orbit_time: 365.0,
-            day: 1.0,
-            radius: 6.1e3,
+            orbit: Length::from_km(14e6),
+            orbit_time: Duration::from_days(365.0),
+            day: Duration::from_days(1.0),
+            radius: Length::from_km(6.1e3),
             appearance: "earth_gltf02/earth.gltf".into(),
             satellites: vec![Body {
                 name: "moon".into(),
-                orbit: 370e6,
-                orbit_time: 31.0,
-                day: 31.0,
-                radius: 1.7e3,
+                orbit: Length::from_km(370e6),
+                orbit_time: Duration::from_days(31.0),
+                day: Duration::from_days(31.0),
+                radius: Length::from_km(1.7e3),
                 appearance: "moon_gltf01/moon.gltf".into(),
                 satellites: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         }],
+        ..Default::default()
     };
 
     println!("{}", serde_yaml::to_string(&sun).unwrap());
 }
+
+#[test]
+fn test_body_circular_default() {
+    let body: Body = serde_yaml::from_str(
+        "name: moon\norbit: 2.0\norbit_time: 31.0\nday: 31.0\nradius: 1700.0\nappearance: none\nsatellites: []\n",
+    )
+    .unwrap();
+    let elements = body.orbital_elements();
+    assert_eq!(elements, orbit::OrbitalElements::circular(2.0, 31.0));
+    assert_eq!(body.kind, BodyKind::Planet);
+
+    let spawn_point: Body =
+        serde_yaml::from_str("name: ship\nkind: spawn_point\norbit: 0.00026\norbit_time: 0.1\n")
+            .unwrap();
+    assert_eq!(spawn_point.kind, BodyKind::SpawnPoint);
+    assert!(spawn_point.appearance.model.is_none() && spawn_point.satellites.is_empty());
+}
+
+#[test]
+fn test_body_units() {
+    let body: Body = serde_yaml::from_str(
+        "name: moon\norbit: 384400 km\norbit_time: 27.3 d\nday: 655.2 h\nradius: 1737.4\nappearance: none\nsatellites: []\n",
+    )
+    .unwrap();
+    assert!((body.orbit.as_km() - 384400.0).abs() < 1e-6);
+    assert_eq!(body.orbit_time, Duration::from_days(27.3));
+    assert!((body.day.as_days() - 27.3).abs() < 1e-9);
+    assert_eq!(body.radius, Length::from_km(1737.4));
+
+    let error = serde_yaml::from_str::<Body>(
+        "name: moon\norbit: 1 parsec\norbit_time: 1\nday: 1\nradius: 1\nappearance: none\nsatellites: []\n",
+    )
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("unknown length unit 'parsec'"));
+}
@@ -0,0 +1,92 @@
+//! Star light.
+//!
+//! Bevy draws a single directional light, so it is aimed at the body in view: it shines from the
+//! light source (stars, unless their appearance says otherwise) that lights that body best, with the
+//! illuminance falling off with the square of the true distance. Other bodies share its direction,
+//! which is close enough for the moons and ships near the viewed body. The shadow projection is
+//! fitted around the viewed body every frame. All light sources of a system shine with the
+//! luminosity of its entry in the universe (see `galaxy::SystemEntry`), that of the sun otherwise.
+use bevy::{math::DVec3, prelude::*};
+
+use crate::quantity::Length;
+
+/// illuminance of sunlight at 1 AU, in lux
+pub const SOLAR_ILLUMINANCE: f32 = 127_500.0;
+/// shadow bounds relative to the radius of the viewed body, large enough for rings and close moons
+const SHADOW_MARGIN: f32 = 3.0;
+
+/// Illuminance (lux) at `distance` from a star of `luminosity` (in solar luminosities)
+pub fn illuminance(distance: Length, luminosity: f32) -> f32 {
+    let au = distance.as_au().max(f64::EPSILON);
+    SOLAR_ILLUMINANCE * luminosity * (1.0 / (au * au)) as f32
+}
+
+/// The light source lighting a body at true `position` best, as an index into `lights` (true
+/// positions)
+pub fn brightest(position: DVec3, lights: impl IntoIterator<Item = DVec3>) -> Option<usize> {
+    lights
+        .into_iter()
+        .map(|light| light.distance_squared(position))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(index, _)| index)
+}
+
+/// Up vector for looking along `direction`: Y, unless that is (nearly) parallel to the direction
+pub fn up(direction: Vec3) -> Vec3 {
+    if direction.normalize().cross(Vec3::Y).length_squared() < 1e-6 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+/// Shadow projection of a directional light shining along `direction` that covers a body of
+/// `radius` at `center` (render space). Bevy places the shadow view of directional lights at the
+/// origin looking along the light, so the bounds are offset to the body.
+pub fn shadow_projection(direction: Vec3, center: Vec3, radius: f32) -> OrthographicProjection {
+    let view = Mat4::look_at_rh(Vec3::ZERO, direction, up(direction));
+    let center = view.transform_point3(center);
+    let half_size = radius * SHADOW_MARGIN;
+    OrthographicProjection {
+        left: center.x - half_size,
+        right: center.x + half_size,
+        bottom: center.y - half_size,
+        top: center.y + half_size,
+        // the view looks along -z
+        near: -center.z - half_size,
+        far: -center.z + half_size,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_lighting() {
+    assert!((illuminance(Length::from_au(1.0), 1.0) - SOLAR_ILLUMINANCE).abs() < 1.0);
+    // neptune gets about a thousandth of the light at the earth
+    let neptune = illuminance(Length::from_au(30.07), 1.0);
+    assert!((neptune - SOLAR_ILLUMINANCE / 904.2).abs() < 1.0);
+    // sirius is 25 times as bright as the sun, its planet at 5 AU gets the light of the earth
+    let sirius = illuminance(Length::from_au(5.0), 25.0);
+    assert!((sirius - SOLAR_ILLUMINANCE).abs() < 1.0);
+
+    let stars = [DVec3::ZERO, DVec3::new(10.0, 0.0, 0.0)];
+    assert_eq!(brightest(DVec3::new(8.0, 1.0, 0.0), stars), Some(1));
+    assert_eq!(brightest(DVec3::new(2.0, 0.0, 0.0), stars), Some(0));
+    assert_eq!(brightest(DVec3::ZERO, []), None);
+
+    // the body is inside the shadow volume, centered
+    let direction = Vec3::new(1.0, -0.2, 0.5).normalize();
+    let center = Vec3::new(3.0, 1.0, -2.0);
+    let projection = shadow_projection(direction, center, 0.5);
+    let view = Mat4::look_at_rh(Vec3::ZERO, direction, Vec3::Y);
+    let p = view.transform_point3(center);
+    assert!((p.x - (projection.left + projection.right) / 2.0).abs() < 1e-5);
+    assert!((p.y - (projection.bottom + projection.top) / 2.0).abs() < 1e-5);
+    assert!((-p.z - (projection.near + projection.far) / 2.0).abs() < 1e-5);
+    assert!((projection.right - projection.left - 3.0).abs() < 1e-5);
+    // light straight down
+    assert_eq!(up(Vec3::new(0.0, -2.0, 0.0)), Vec3::Z);
+    let vertical = shadow_projection(-Vec3::Y, center, 0.5);
+    assert!(vertical.left.is_finite() && vertical.near.is_finite());
+}
@@ -0,0 +1,260 @@
+//! Loading screen.
+//!
+//! When a star system is spawned, all of its appearance assets are requested up front (see
+//! `LoadingStatus::request`) and the app enters `AppState::Loading`. It moves on to
+//! `AppState::Flying` once every asset is loaded or has failed. Failed assets stay listed on the
+//! loading screen (`HudSrc::LoadingScreen`).
+use std::collections::BTreeSet;
+
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{appearance::Appearance, Body};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// waiting for the assets of the star system
+    Loading,
+    Flying,
+}
+
+/// Model files (and cloud textures) of all bodies and belts of a system, relative to the asset root
+pub fn appearance_paths(sun: &Body) -> Vec<String> {
+    let mut paths = BTreeSet::new();
+    for body in sun
+        .iter_tree()
+        .into_iter()
+        .filter(|body| !body.kind.is_virtual())
+    {
+        paths.extend(body.appearance.model_path());
+        if let Some(clouds) = &body.appearance.clouds {
+            paths.insert(format!("models/{}", clouds.texture));
+        }
+        for belt in body.belts.iter().filter(|belt| !belt.appearance.is_empty()) {
+            paths.insert(format!("models/{}", belt.appearance));
+        }
+    }
+    paths.into_iter().collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadingProgress {
+    pub total: usize,
+    pub loaded: usize,
+    /// paths of the assets that failed to load
+    pub failed: Vec<String>,
+}
+
+impl LoadingProgress {
+    pub fn new<'a>(states: impl IntoIterator<Item = (&'a str, LoadState)>) -> Self {
+        let mut progress = LoadingProgress::default();
+        for (path, state) in states {
+            progress.total += 1;
+            match state {
+                LoadState::Loaded => progress.loaded += 1,
+                LoadState::Failed => progress.failed.push(path.to_string()),
+                _ => (),
+            }
+        }
+        progress
+    }
+
+    /// every asset is either loaded or failed
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed.len() == self.total
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = if self.is_done() {
+            format!("loaded {} of {} assets", self.loaded, self.total)
+        } else {
+            format!(
+                "loading assets: {} of {}",
+                self.loaded + self.failed.len(),
+                self.total
+            )
+        };
+        for path in self.failed.iter() {
+            text += &format!("\nfailed: {}", path);
+        }
+        text
+    }
+}
+
+/// The assets requested for the current star system
+#[derive(Default)]
+pub struct LoadingStatus {
+    /// None until the first star system requested its assets
+    handles: Option<Vec<(String, HandleUntyped)>>,
+    pub progress: LoadingProgress,
+}
+
+impl LoadingStatus {
+    /// Start loading `paths`, replacing the previous request. The handles are kept, so the assets
+    /// stay loaded for the bodies using them.
+    pub fn request(&mut self, asset_server: &AssetServer, paths: Vec<String>) {
+        self.progress = LoadingProgress {
+            total: paths.len(),
+            ..Default::default()
+        };
+        self.handles = Some(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let handle = asset_server.load_untyped(path.as_str());
+                    (path, handle)
+                })
+                .collect(),
+        );
+    }
+
+    pub fn is_failed(&self, path: &str) -> bool {
+        self.progress.failed.iter().any(|failed| failed == path)
+    }
+
+    /// the body is drawn procedurally: it has no model, or the model failed to load
+    pub fn is_procedural(&self, appearance: &Appearance) -> bool {
+        appearance
+            .model_path()
+            .map_or(true, |model| self.is_failed(&model))
+    }
+
+    pub fn text(&self) -> String {
+        match self.handles {
+            Some(_) => self.progress.text(),
+            None => "waiting for star system".to_string(),
+        }
+    }
+}
+
+pub fn loading_system(
+    asset_server: Res<AssetServer>,
+    mut status: ResMut<LoadingStatus>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let handles = match &status.handles {
+        Some(handles) => handles,
+        None => return,
+    };
+    let progress = LoadingProgress::new(
+        handles
+            .iter()
+            .map(|(path, handle)| (path.as_str(), asset_server.get_load_state(handle))),
+    );
+    if progress.is_done() {
+        for path in progress.failed.iter() {
+            error!("failed to load {}", path);
+        }
+        info!("loaded {} of {} assets", progress.loaded, progress.total);
+        if let Err(err) = app_state.set(AppState::Flying) {
+            warn!("cannot leave loading state: {:?}", err);
+        }
+    }
+    status.progress = progress;
+}
+
+#[derive(Default)]
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingStatus>()
+            .add_state(AppState::Loading)
+            .add_system_set(
+                SystemSet::on_update(AppState::Loading).with_system(loading_system.system()),
+            );
+    }
+}
+
+#[test]
+fn test_loading_progress() {
+    let sun: Body = serde_yaml::from_str(
+        r#"
+name: sun
+kind: star
+orbit: 0
+orbit_time: 0
+appearance: sun/sun.gltf
+satellites:
+  - name: earth
+    orbit: 1.0
+    orbit_time: 365.25
+    appearance: earth/earth.gltf
+    satellites:
+      - name: l1
+        kind: spawn_point
+        orbit: 0.01
+        orbit_time: 365.25
+        appearance: station/station.gltf
+  - name: copy
+    orbit: 2.0
+    orbit_time: 700
+    appearance:
+      model: earth/earth.gltf
+      clouds: { texture: earth/clouds.png, height: 10 km }
+  - name: rock
+    orbit: 3.0
+    orbit_time: 1800
+    appearance: none
+"#,
+    )
+    .unwrap();
+    let paths = appearance_paths(&sun);
+    assert_eq!(
+        paths,
+        [
+            "models/earth/clouds.png",
+            "models/earth/earth.gltf",
+            "models/sun/sun.gltf"
+        ]
+    );
+
+    let loading = LoadingProgress::new([
+        (paths[0].as_str(), LoadState::Loaded),
+        (paths[1].as_str(), LoadState::Loading),
+    ]);
+    assert!(!loading.is_done());
+    assert_eq!(loading.text(), "loading assets: 1 of 2");
+
+    let done = LoadingProgress::new([
+        (paths[0].as_str(), LoadState::Loaded),
+        (paths[1].as_str(), LoadState::Failed),
+    ]);
+    assert!(done.is_done());
+    assert_eq!(
+        done.text(),
+        "loaded 1 of 2 assets\nfailed: models/earth/earth.gltf"
+    );
+    assert!(LoadingProgress::new([]).is_done());
+}
+
+#[test]
+fn test_missing_model() {
+    let sun: Body = serde_yaml::from_str(
+        r#"
+name: sun
+kind: star
+orbit: 0
+orbit_time: 0
+appearance: none
+satellites:
+  - name: earth
+    orbit: 1.0
+    orbit_time: 365.25
+    appearance: earth/missing.gltf
+"#,
+    )
+    .unwrap();
+    // the system is spawned
+    let errors = crate::validate::validate(&sun, std::path::Path::new("assets"));
+    assert_eq!(errors.len(), 1);
+    assert!(!errors[0].is_error());
+
+    let paths = appearance_paths(&sun);
+    assert_eq!(paths, ["models/earth/missing.gltf"]);
+    let mut status = LoadingStatus::default();
+    let earth = &sun.satellites[0].appearance;
+    assert!(!status.is_procedural(earth));
+    status.progress = LoadingProgress::new([(paths[0].as_str(), LoadState::Failed)]);
+    assert!(status.is_procedural(earth));
+    assert!(status.is_procedural(&sun.appearance));
+}
@@ -0,0 +1,119 @@
+//! Level of detail for bodies.
+//!
+//! Every spawned body has three representations: its full model, a low-poly sphere and an impostor,
+//! a small unlit sphere kept at a constant apparent size so distant bodies stay visible as points.
+//! Which one is shown depends on the apparent angular size of the body from the camera. The levels
+//! switch with some hysteresis so bodies near a threshold don't flicker.
+use bevy::prelude::*;
+
+/// Representation of a body, ordered by detail. Also marks the entity drawing each level.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lod {
+    Impostor,
+    Sphere,
+    Full,
+}
+
+/// The level currently shown for a body
+#[derive(Component, Debug)]
+pub struct LevelOfDetail {
+    pub level: Lod,
+}
+
+impl Default for LevelOfDetail {
+    fn default() -> Self {
+        LevelOfDetail { level: Lod::Full }
+    }
+}
+
+/// Angular sizes (radians) at which the levels switch
+#[derive(Clone, Copy, Debug)]
+pub struct LodThresholds {
+    /// the full model is shown above this
+    pub full: f32,
+    /// the sphere is shown above this, the impostor below
+    pub sphere: f32,
+    /// relative margin around the thresholds, the level only changes once the angular size is
+    /// farther than this from a threshold
+    pub hysteresis: f32,
+}
+
+impl Default for LodThresholds {
+    fn default() -> Self {
+        LodThresholds {
+            full: 0.02,
+            sphere: IMPOSTOR_ANGLE,
+            hysteresis: 0.2,
+        }
+    }
+}
+
+/// apparent size of the impostor (radians), a few pixels
+pub const IMPOSTOR_ANGLE: f32 = 0.004;
+
+/// Apparent angular diameter (radians) of a sphere of `radius` at `distance` from its center
+pub fn angular_size(radius: f32, distance: f32) -> f32 {
+    if distance <= radius {
+        return std::f32::consts::PI;
+    }
+    2.0 * (radius / distance).asin()
+}
+
+/// Radius of the impostor at `distance`, so that it appears `IMPOSTOR_ANGLE` large
+pub fn impostor_radius(distance: f32) -> f32 {
+    distance * (IMPOSTOR_ANGLE / 2.0).sin()
+}
+
+impl Lod {
+    fn for_size(angular_size: f32, thresholds: &LodThresholds, margin: f32) -> Lod {
+        if angular_size >= thresholds.full * margin {
+            Lod::Full
+        } else if angular_size >= thresholds.sphere * margin {
+            Lod::Sphere
+        } else {
+            Lod::Impostor
+        }
+    }
+
+    /// The level to show next, for a body currently shown at `self`
+    pub fn select(self, angular_size: f32, thresholds: &LodThresholds) -> Lod {
+        let margin = 1.0 + thresholds.hysteresis;
+        // more detail only once clearly above a threshold, less only once clearly below
+        let raise = Lod::for_size(angular_size, thresholds, margin);
+        let lower = Lod::for_size(angular_size, thresholds, 1.0 / margin);
+        if raise > self {
+            raise
+        } else if lower < self {
+            lower
+        } else {
+            self
+        }
+    }
+}
+
+#[test]
+fn test_lod() {
+    let thresholds = LodThresholds::default();
+    // the earth seen from the moon is about 2 degrees large
+    let earth = angular_size(6_371.0, 384_400.0);
+    assert!((earth.to_degrees() - 1.9).abs() < 0.05);
+    assert_eq!(angular_size(1.0, 0.5), std::f32::consts::PI);
+    assert!((angular_size(impostor_radius(1e6), 1e6) - IMPOSTOR_ANGLE).abs() < 1e-6);
+
+    assert_eq!(Lod::Impostor.select(earth, &thresholds), Lod::Full);
+    assert_eq!(Lod::Full.select(1e-5, &thresholds), Lod::Impostor);
+    // within the hysteresis band the level sticks
+    let near_full = thresholds.full * 1.1;
+    assert_eq!(Lod::Sphere.select(near_full, &thresholds), Lod::Sphere);
+    assert_eq!(Lod::Full.select(near_full, &thresholds), Lod::Full);
+    let below_full = thresholds.full * 0.9;
+    assert_eq!(Lod::Full.select(below_full, &thresholds), Lod::Full);
+    assert_eq!(
+        Lod::Full.select(thresholds.full * 0.5, &thresholds),
+        Lod::Sphere
+    );
+    assert_eq!(
+        Lod::Sphere.select(thresholds.full * 1.5, &thresholds),
+        Lod::Full
+    );
+}
@@ -1,24 +1,41 @@
 use bevy::{
-    asset::Asset,
+    asset::{Asset, AssetServerSettings, LoadState},
     diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    ecs::system::EntityCommands,
+    math::DVec3,
+    pbr::{NotShadowCaster, NotShadowReceiver},
     prelude::*,
     reflect::TypeRegistry,
     render::primitives::Frustum,
     scene::InstanceId,
+    utils::HashMap,
 };
 use bevy_egui::EguiPlugin;
 use heron::*;
 use universe::{
-    hud_egui::{hud_egui_setup_system, HudEguiPlugin},
+    appearance::{Appearance, MaterialSpec},
+    ephemeris,
+    floating_origin::{FloatingOrigin, FloatingOriginPlugin},
+    galaxy::{self, GalaxyPlugin, SystemEntry, Universe},
+    generator,
+    hud::HudElement,
+    hud_egui::{hud_egui_setup_system, HudEguiPlugin, HudOrder},
+    lighting,
+    loading::{self, AppState, LoadingPlugin, LoadingStatus},
+    lod::{self, LevelOfDetail, Lod, LodThresholds},
     prelude::*,
-    property,
+    procedural::{self, ProceduralAppearance},
+    property::{self, PropertyName, PropertyUpdateEvent, PropertyValue},
+    quantity::Length,
+    save_game::{ClockState, SaveGame, ShipState, SAVE_GAME_VERSION},
+    scale::{ScaleMode, ScalePlugin},
+    sim_clock::{self, SimClock},
+    soi::{SoiPlugin, SphereOfInfluence},
+    system_asset::{SystemAssetPlugin, SystemLoadErrors},
+    universal_position::{self, DisplayPosition, UniversalPosition, UniversalPositionPlugin},
+    validate, BodyKind,
 };
 
-#[derive(Component)]
-struct Planet {
-    vel: f32,
-}
-
 #[derive(Component, Reflect)]
 struct Center {
     vel: f32,
@@ -28,9 +45,38 @@ struct Center {
 
 #[derive(Component)]
 struct BodyAppearance {
-    radius: f32,
-    model: String,
+    kind: BodyKind,
+    /// true radius, see `Scaled` for the drawn one
+    radius: Length,
+    spec: Appearance,
+    /// used if there is no model or it fails to load
+    procedural: ProceduralAppearance,
+    light_source: bool,
+    /// true height of the atmosphere shell
+    atmosphere_height: Length,
     vel: f32,
+    /// radians, the spin axis is tilted around the x axis of the parent frame
+    axial_tilt: f32,
+}
+
+impl BodyAppearance {
+    fn from_body(body: &universe::Body) -> Self {
+        let rotation_period = body.rotation_period().as_days() as f32;
+        BodyAppearance {
+            kind: body.kind,
+            radius: body.radius,
+            spec: body.appearance.clone(),
+            procedural: ProceduralAppearance::for_body(body),
+            light_source: body.appearance.is_light_source(body.kind),
+            atmosphere_height: body.atmosphere_height.unwrap_or(body.radius * 0.02),
+            vel: if rotation_period > 0.0 {
+                1.0 / rotation_period
+            } else {
+                0.0
+            },
+            axial_tilt: body.axial_tilt.as_radians() as f32,
+        }
+    }
 }
 
 impl Center {
@@ -43,9 +89,83 @@ impl Center {
     }
 }
 
+/// A mesh drawn with a radius that depends on the scale mode
+#[derive(Component, Clone, Copy)]
+struct Scaled {
+    kind: BodyKind,
+    /// true radius
+    radius: Length,
+    /// applied on top of the drawn radius, for models that are not a unit sphere
+    correction: f32,
+}
+
+impl Scaled {
+    fn scale(&self, scale_mode: ScaleMode) -> Vec3 {
+        let radius = scale_mode.radius(self.kind, self.radius).as_units() as f32;
+        Vec3::splat(radius * self.correction)
+    }
+}
+
 #[derive(Component)]
 struct Rotation {
     vel: f32,
+    tilt: f32,
+}
+
+// body kind markers
+#[derive(Component)]
+struct Star;
+#[derive(Component)]
+struct Planet;
+#[derive(Component)]
+struct Moon;
+#[derive(Component)]
+struct Asteroid;
+#[derive(Component)]
+struct Station;
+#[derive(Component)]
+struct SpawnPoint;
+#[derive(Component)]
+struct Barycenter;
+
+/// The spawn point where the ship is created
+#[derive(Component)]
+struct ShipSpawn;
+
+fn insert_kind_marker(entity: &mut EntityCommands, kind: BodyKind) {
+    match kind {
+        BodyKind::Star => entity.insert(Star),
+        BodyKind::Planet => entity.insert(Planet),
+        BodyKind::Moon => entity.insert(Moon),
+        BodyKind::Asteroid => entity.insert(Asteroid),
+        BodyKind::Station => entity.insert(Station),
+        BodyKind::SpawnPoint => entity.insert(SpawnPoint),
+        BodyKind::Barycenter => entity.insert(Barycenter),
+    };
+}
+
+/// The expanded rocks of a belt, waiting to be spawned
+#[derive(Component)]
+struct BeltRocks {
+    /// the Center the rocks orbit
+    center: Entity,
+    appearance: String,
+    rocks: Vec<universe::belt::Rock>,
+}
+
+/// A single rock of a belt. Rocks share mesh and material and have no `Center`, only an `Orbit`.
+#[derive(Component)]
+struct BeltRock {
+    name: String,
+    /// true radius, also used for the collider
+    radius: Length,
+}
+
+#[derive(Component)]
+struct Orbit {
+    elements: universe::orbit::OrbitalElements,
+    /// the Center (or star) this orbit is relative to
+    parent: Entity,
 }
 
 enum InSystemVisibility {
@@ -53,13 +173,68 @@ enum InSystemVisibility {
     Local,
 }
 
+/// A star of another system, drawn as a point on the sky
+#[derive(Component)]
+struct DistantStar {
+    /// galactic coordinates in light years
+    position: DVec3,
+    luminosity: f32,
+}
+
+/// Marks the HUD element listing the travel destinations
+#[derive(Component)]
+struct DestinationSelect;
+
+/// Command line options
+#[derive(Default)]
+struct StartupOptions {
+    /// use a procedurally generated system instead of loading the universe's start system
+    generate: Option<u64>,
+    universe: Option<String>,
+}
+
+impl StartupOptions {
+    // parsed before the log plugin is added, so problems go to stderr
+    fn from_args() -> Self {
+        let mut options = StartupOptions::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--generate" => match args.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => options.generate = Some(seed),
+                    _ => eprintln!("--generate expects a numeric seed"),
+                },
+                "--universe" => match args.next() {
+                    Some(path) => options.universe = Some(path),
+                    None => eprintln!("--universe expects a file name"),
+                },
+                _ => eprintln!("ignoring unknown argument {}", arg),
+            }
+        }
+        options
+    }
+}
+
 fn main() {
     App::new()
+        .insert_resource(StartupOptions::from_args())
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..Default::default()
+        })
         .add_plugins(DefaultPlugins)
         .add_plugin(PhysicsPlugin::default())
         .add_plugin(EguiPlugin)
         .add_plugin(HudEguiPlugin)
         .add_plugin(property::PropertyPlugin)
+        .add_plugin(sim_clock::SimClockPlugin)
+        .add_plugin(SystemAssetPlugin)
+        .add_plugin(GalaxyPlugin)
+        .add_plugin(FloatingOriginPlugin)
+        .add_plugin(UniversalPositionPlugin)
+        .add_plugin(ScalePlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(SoiPlugin)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(EntityCountDiagnosticsPlugin::default())
         .register_type::<Center>()
@@ -72,23 +247,95 @@ fn main() {
         .insert_resource(Msaa { samples: 4 })
         .insert_resource(ClearColor(Color::BLACK))
         .add_startup_system(setup)
-        .add_system(spawn_planets)
-        .insert_resource(SpawnTimer {
-            timer: Timer::from_seconds(0.100, true),
-        })
+        .add_system(star_system_asset_system)
+        .add_system(star_system_load_failed_system)
+        .add_system(universe_asset_system)
+        .add_system(destination_property_system)
+        .add_system(interstellar_travel_system)
+        .add_system(distant_star_system)
+        .add_system(spawn_belt_rocks)
+        .add_system(belt_rock_proximity_system)
         .add_system(spawn_ship)
-        .add_system(dump_asset_status)
-        .add_system(animate_light_direction)
+        .add_system(save_game_system)
+        .add_system(load_game_system)
+        .add_system(star_light_system)
         .add_system(animate_camera)
-        .add_system(turn_earth)
-        // .add_system(rotation_system)
-        .add_system(ship::acceleration_system)
+        .add_system(orbit_system)
+        .add_system(scale_system)
+        .add_system(lod_system)
+        .add_system(scene_ready_system)
+        .init_resource::<LodThresholds>()
+        .init_resource::<LoadedShips>()
+        .add_system(rotation_system)
         .add_system(ship::update_properties_system)
+        // bodies are spawned and the ship is flown once their assets are loaded
+        .add_system_set(
+            SystemSet::on_update(AppState::Flying)
+                .with_system(spawn_planets)
+                .with_system(ship::acceleration_system),
+        )
         .run();
 }
 
-struct SpawnTimer {
-    timer: Timer,
+/// The currently loaded star system and the node holding its spawned `Center`s
+struct StarSystem {
+    handle: Handle<universe::Body>,
+    root: Option<Entity>,
+}
+
+const UNIVERSE_PATH: &str = "local.universe.yaml";
+/// wall-clock duration of an interstellar jump
+const TRAVEL_SECONDS: f32 = 8.0;
+/// distant stars are drawn on a sphere of this radius around the ship, inside the camera's far plane
+const SKY_DISTANCE: f32 = 30.0 * AU_TO_UNIT;
+
+/// An interstellar jump in progress
+struct Travel {
+    /// galactic position of the departure system
+    from: DVec3,
+    to: String,
+    /// 0..1
+    progress: f32,
+}
+
+/// The universe and where in it the ship is
+struct UniverseState {
+    handle: Handle<Universe>,
+    /// the system spawned in full detail, None before the universe is loaded
+    current: Option<String>,
+    destination: Option<String>,
+    travel: Option<Travel>,
+    /// move the ship to the spawn point once the destination system is spawned
+    arriving: bool,
+}
+
+impl UniverseState {
+    /// galactic position of the ship in light years
+    fn position(&self, universe: &Universe) -> Option<DVec3> {
+        match &self.travel {
+            Some(travel) => universe
+                .find(&travel.to)
+                .map(|to| galaxy::travel_position(travel.from, to.position(), travel.progress)),
+            None => self
+                .current
+                .as_ref()
+                .and_then(|current| universe.find(current))
+                .map(SystemEntry::position),
+        }
+    }
+}
+
+fn system_handle(
+    entry: &SystemEntry,
+    asset_server: &AssetServer,
+    bodies: &mut Assets<universe::Body>,
+) -> Handle<universe::Body> {
+    match (&entry.file, entry.generate) {
+        (Some(file), _) => asset_server.load(file.as_str()),
+        (None, Some(seed)) => bodies.add(generator::generate(seed)),
+        // rejected by Universe::check
+        (None, None) => Handle::default(),
+    }
 }
 
 #[derive(Bundle, Clone, Default)]
@@ -99,75 +346,210 @@ struct TransformNodeBundle {
     transform: Transform,
 }
 
-fn planet_bundle(name: &str, radius: f32, asset_server: &AssetServer) -> PbrBundle {
-    let earth_handle = asset_server.load(&format!("{}#Mesh0/Primitive0", name));
-    // You can also add assets directly to their Assets<T> storage:
+/// The part of a model drawn at full detail
+enum ModelPart {
+    Mesh(Handle<Mesh>),
+    Scene(Handle<Scene>),
+}
 
-    let material_handle = asset_server.load(&format!("{}#Material0", name));
+/// `material` with the overrides of the appearance applied
+fn override_material(
+    appearance: &BodyAppearance,
+    material: Handle<StandardMaterial>,
+    materials: &mut Assets<StandardMaterial>,
+) -> Handle<StandardMaterial> {
+    let spec = &appearance.spec;
+    if !spec.material.has_overrides() && !appearance.light_source {
+        return material;
+    }
+    // the model is loaded by now, see `loading`
+    let mut overridden = match materials.get(&material) {
+        Some(material) => material.clone(),
+        None => {
+            warn!(
+                "material {:?} not found, not applying the overrides",
+                spec.material_path()
+            );
+            return material;
+        }
+    };
+    spec.material
+        .apply(&mut overridden, appearance.light_source);
+    materials.add(overridden)
+}
 
-    PbrBundle {
-        mesh: earth_handle,
-        material: material_handle,
-        transform: Transform::from_scale(Vec3::new(radius, radius, radius)),
-        ..Default::default()
+/// The part of the model selected by the appearance and the material for the low detail sphere.
+/// None if the body has no model.
+fn model_assets(
+    appearance: &BodyAppearance,
+    asset_server: &AssetServer,
+    materials: &mut Assets<StandardMaterial>,
+) -> Option<(ModelPart, Handle<StandardMaterial>)> {
+    let spec = &appearance.spec;
+    let part = match spec.mesh_path() {
+        Some(mesh) => ModelPart::Mesh(asset_server.load(mesh.as_str())),
+        None => ModelPart::Scene(asset_server.load(spec.scene_path()?.as_str())),
+    };
+    let material = asset_server.load(spec.material_path()?.as_str());
+    Some((part, override_material(appearance, material, materials)))
+}
+
+/// The node below a Center that a glTF scene is spawned into. It carries the scale and spin of the
+/// body, the scene keeps its own node transforms.
+#[derive(Component)]
+struct SceneRoot {
+    instance: InstanceId,
+    /// the entities of the scene that have a mesh, once the scene is spawned
+    meshes: Option<Vec<Entity>>,
+}
+
+/// Meshes and materials shared by the lower levels of detail of all bodies
+struct LodAssets {
+    /// the full level of procedural bodies
+    uv_sphere: Handle<Mesh>,
+    sphere: Handle<Mesh>,
+    impostor: Handle<Mesh>,
+    impostor_material: Handle<StandardMaterial>,
+    star_impostor_material: Handle<StandardMaterial>,
+}
+
+/// Shared by all Centers spawned for a system
+struct SpawnContext<'a> {
+    t: f64,
+    scale_mode: ScaleMode,
+    /// name of the spawn point where the ship starts
+    ship_spawn: Option<&'a str>,
+}
+
+// Centers are spawned side by side under the system node, each orbit refers to its parent Center.
+// Nested transforms would add up f32 translations at AU scale, instead the render transforms are
+// derived from the UniversalPosition (or DisplayPosition) of every Center.
+fn spawn_satellites(
+    parent_body: &universe::Body,
+    parent: Entity,
+    parent_position: DVec3,
+    parent_display: DVec3,
+    context: &SpawnContext,
+    f: &mut ChildBuilder,
+) {
+    for body in parent_body.satellites.iter() {
+        let elements = body.orbital_elements();
+        let offset = orbit_offset(&elements, context.t);
+        let position = parent_position + offset;
+        let display = parent_display
+            + context
+                .scale_mode
+                .orbit_offset(offset, parent_body.kind, parent_body.radius);
+        let mut entity = f.spawn_bundle(TransformNodeBundle::default());
+        entity
+            .insert(Orbit { elements, parent })
+            .insert(UniversalPosition(position))
+            .insert(DisplayPosition(display))
+            .insert(Center::new(&body.name))
+            .insert(BodyAppearance::from_body(body));
+        //.insert(Rotation { vel })
+        if let Some(soi) = SphereOfInfluence::new(body, parent, parent_body) {
+            entity.insert(soi);
+        }
+        insert_kind_marker(&mut entity, body.kind);
+        if context.ship_spawn == Some(body.name.as_str()) {
+            entity.insert(ShipSpawn);
+        }
+        let entity = entity.id();
+        spawn_satellites(body, entity, position, display, context, f);
+        spawn_belts(body, entity, f);
     }
 }
 
-fn spawn_satellites(bodies: &[universe::Body], f: &mut ChildBuilder) {
-    for body in bodies.iter() {
-        let oribit_vel = if body.orbit_time > 0.0 {
-            1.0 / body.orbit_time
-        } else {
-            0.0
-        };
-        let vel = if body.day > 0.0 { 1.0 / body.day } else { 0.0 };
-        f.spawn_bundle(TransformNodeBundle::default())
-            .insert(Rotation { vel: oribit_vel })
-            .with_children(|f| {
-                f.spawn_bundle(TransformNodeBundle {
-                    transform: Transform::from_translation(Vec3::new(
-                        0.0,
-                        0.0,
-                        body.orbit * AU_TO_UNIT * ORBIT_MUL,
-                    )),
-                    ..Default::default()
-                })
-                .insert(Center::new(&body.name))
-                .insert(BodyAppearance {
-                    radius: body.radius * KM_TO_UNIT,
-                    model: body.appearance.clone(),
-                    vel,
-                })
-                //.insert(Rotation { vel })
-                .with_children(|f| {
-                    spawn_satellites(&body.satellites, f);
-                });
-            });
+// belts get a single node each, the rocks are added by spawn_belt_rocks
+fn spawn_belts(body: &universe::Body, center: Entity, f: &mut ChildBuilder) {
+    for belt in body.belts.iter() {
+        match belt.rocks(body) {
+            Some(rocks) => {
+                f.spawn_bundle(TransformNodeBundle::default())
+                    .insert(BeltRocks {
+                        center,
+                        appearance: belt.appearance.clone(),
+                        rocks,
+                    });
+            }
+            None => warn!("belt {} around {} without mass", belt.name, body.name),
+        }
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let sun: universe::Body = serde_yaml::from_reader(std::io::BufReader::new(
-        std::fs::File::open("assets/terra_au.yaml").unwrap(),
-    ))
-    .unwrap();
+/// Spawn the Centers of the system at time `t` under a new, untransformed node
+fn spawn_star_system(
+    commands: &mut Commands,
+    sun: &universe::Body,
+    t: f64,
+    scale_mode: ScaleMode,
+) -> Entity {
+    let context = SpawnContext {
+        t,
+        scale_mode,
+        ship_spawn: sun.start_spawn_point().map(|body| body.name.as_str()),
+    };
 
     commands
         .spawn_bundle(TransformNodeBundle::default())
-        .insert(Center::new(&sun.name))
-        .insert(BodyAppearance {
-            radius: sun.radius * KM_TO_UNIT,
-            model: sun.appearance,
-            vel: 0.0,
-        })
         .with_children(|f| {
-            spawn_satellites(&sun.satellites, f);
+            let mut entity = f.spawn_bundle(TransformNodeBundle::default());
+            entity
+                .insert(UniversalPosition::default())
+                .insert(DisplayPosition::default())
+                .insert(Center::new(&sun.name))
+                .insert(BodyAppearance::from_body(sun))
+                .insert(SphereOfInfluence::root(sun));
+            insert_kind_marker(&mut entity, sun.kind);
+            let entity = entity.id();
+            spawn_satellites(sun, entity, DVec3::ZERO, DVec3::ZERO, &context, f);
+            spawn_belts(sun, entity, f);
         })
-        .id();
+        .id()
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    startup_options: Res<StartupOptions>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(LodAssets {
+        uv_sphere: meshes.add(procedural::sphere_mesh()),
+        sphere: meshes.add(Mesh::from(shape::Icosphere {
+            radius: 1.0,
+            subdivisions: 3,
+        })),
+        impostor: meshes.add(Mesh::from(shape::Icosphere {
+            radius: 1.0,
+            subdivisions: 1,
+        })),
+        impostor_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.7, 0.7, 0.7),
+            unlit: true,
+            ..Default::default()
+        }),
+        star_impostor_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1.0, 0.95, 0.8),
+            unlit: true,
+            ..Default::default()
+        }),
+    });
+    // the star system is chosen once the universe is loaded
+    commands.insert_resource(StarSystem {
+        handle: Handle::default(),
+        root: None,
+    });
+    let universe_path = startup_options.universe.as_deref().unwrap_or(UNIVERSE_PATH);
+    commands.insert_resource(UniverseState {
+        handle: asset_server.load(universe_path),
+        current: None,
+        destination: None,
+        travel: None,
+        arriving: false,
+    });
 
     // Cube (with radius)
     // let ship = commands
@@ -197,18 +579,9 @@ fn setup(
     //     })
     //     .id();
 
-    const HALF_SIZE: f32 = 1.0;
+    // aimed by star_light_system
     commands.spawn_bundle(DirectionalLightBundle {
         directional_light: DirectionalLight {
-            shadow_projection: OrthographicProjection {
-                left: -HALF_SIZE,
-                right: HALF_SIZE,
-                bottom: -HALF_SIZE,
-                top: HALF_SIZE,
-                near: -10.0 * HALF_SIZE,
-                far: 10.0 * HALF_SIZE,
-                ..Default::default()
-            },
             shadows_enabled: true,
             ..Default::default()
         },
@@ -216,129 +589,968 @@ fn setup(
     });
 }
 
-fn spawn_planets(
-    time: Res<Time>,
-    mut spawn_timer: ResMut<SpawnTimer>,
+// (re-)spawn the Center hierarchy whenever the system handle changes (new system, loaded game,
+// arrival after interstellar travel) or the system description is modified on disk. The bodies get
+// their models once all appearance assets of the system are loaded, see `loading`.
+#[allow(clippy::too_many_arguments)]
+fn star_system_asset_system(
     mut commands: Commands,
+    mut events: EventReader<AssetEvent<universe::Body>>,
+    mut star_system: ResMut<StarSystem>,
+    mut spawned_handle: Local<Handle<universe::Body>>,
+    bodies: Res<Assets<universe::Body>>,
+    clock: Res<SimClock>,
+    scale_mode: Res<ScaleMode>,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<
-        (Entity, &mut Center, &BodyAppearance, &GlobalTransform), /* , Added<Center>*/
-    >,
+    mut loading_status: ResMut<LoadingStatus>,
+    mut app_state: ResMut<State<AppState>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
 ) {
-    spawn_timer.timer.tick(time.delta());
-    if !spawn_timer.timer.just_finished() {
+    let modified = events
+        .iter()
+        .filter(|event| {
+            matches!(event, AssetEvent::Modified { handle } if *handle == star_system.handle)
+        })
+        .count()
+        > 0;
+    // a system that is already loaded (e.g. when travelling back) sends no Created event
+    if !modified && *spawned_handle == star_system.handle {
         return;
     }
+    let sun = match bodies.get(&star_system.handle) {
+        Some(sun) => sun,
+        None => return,
+    };
+    *spawned_handle = star_system.handle.clone();
+    let errors = validate::validate(sun, std::path::Path::new("assets"));
+    for error in errors.iter() {
+        if error.is_error() {
+            error!("{}", error);
+        } else {
+            warn!("{}", error);
+        }
+    }
+    let text = errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    property_update_events.send(PropertyUpdateEvent::new(
+        "system.errors".to_string(),
+        PropertyValue::String(text),
+    ));
+    // keep the previous hierarchy alive if the new description is broken, bodies with missing
+    // models are drawn procedurally
+    if errors.iter().any(|error| error.is_error()) {
+        return;
+    }
+
+    if let Some(root) = star_system.root.take() {
+        info!("despawn star system");
+        commands.entity(root).despawn_recursive();
+    }
+    info!("spawn star system {}", sun.name);
+    star_system.root = Some(spawn_star_system(
+        &mut commands,
+        sun,
+        clock.days(),
+        *scale_mode,
+    ));
+    loading_status.request(&asset_server, loading::appearance_paths(sun));
+    // fails if already loading, which is fine
+    let _ = app_state.overwrite_set(AppState::Loading);
+
+    let bodies = sun.iter_tree();
+    let summary = BodyKind::ALL
+        .iter()
+        .filter_map(|kind| {
+            let count = bodies.iter().filter(|body| body.kind == *kind).count();
+            (count > 0).then(|| format!("{} {}", count, kind.name()))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    property_update_events.send(PropertyUpdateEvent::new(
+        "system.summary".to_string(),
+        PropertyValue::String(summary),
+    ));
+}
+
+// parse errors are logged by the asset loader, point the user there
+fn star_system_load_failed_system(
+    asset_server: Res<AssetServer>,
+    star_system: Res<StarSystem>,
+    universe_state: Res<UniverseState>,
+    load_errors: Res<SystemLoadErrors>,
+    mut reported: Local<bool>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    let failed = [star_system.handle.id, universe_state.handle.id]
+        .into_iter()
+        .find(|id| asset_server.get_load_state(*id) == LoadState::Failed);
+    if let (Some(id), false) = (failed, *reported) {
+        let path = asset_server.get_handle_path(id);
+        // system files keep their errors, other assets only log them
+        let text = path
+            .as_ref()
+            .and_then(|path| load_errors.get(path.path()))
+            .unwrap_or_else(|| format!("failed to load {:?}, see log", path));
+        property_update_events.send(PropertyUpdateEvent::new(
+            "system.errors".to_string(),
+            PropertyValue::String(text),
+        ));
+    }
+    *reported = failed.is_some();
+}
+
+// pick the start system and (re-)create the distant stars whenever the universe is loaded or
+// modified on disk
+#[allow(clippy::too_many_arguments)]
+fn universe_asset_system(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Universe>>,
+    universes: Res<Assets<Universe>>,
+    mut universe_state: ResMut<UniverseState>,
+    mut star_system: ResMut<StarSystem>,
+    startup_options: Res<StartupOptions>,
+    asset_server: Res<AssetServer>,
+    mut bodies: ResMut<Assets<universe::Body>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut hud_order: ResMut<HudOrder>,
+    stale_query: Query<Entity, Or<(With<DistantStar>, With<DestinationSelect>)>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    for event in events.iter() {
+        let universe = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle }
+                if *handle == universe_state.handle =>
+            {
+                match universes.get(handle) {
+                    Some(universe) => universe,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
 
-    for (entity, mut center, appearance, global_transform) in query.iter_mut() {
-        if center.name.starts_with("ship") {
+        if universe_state.current.is_none() {
+            let start = universe
+                .find(&universe.start)
+                .expect("checked by the loader");
+            star_system.handle = match startup_options.generate {
+                Some(seed) => {
+                    info!("generating star system from seed {}", seed);
+                    bodies.add(generator::generate(seed))
+                }
+                None => system_handle(start, &asset_server, &mut bodies),
+            };
+            universe_state.current = Some(start.name.clone());
+            universe_state.destination = Some(start.name.clone());
+            property_update_events.send(PropertyUpdateEvent::new(
+                "universe.system".to_string(),
+                PropertyValue::String(start.name.clone()),
+            ));
+            property_update_events.send(PropertyUpdateEvent::new(
+                "universe.destination".to_string(),
+                PropertyValue::String(start.name.clone()),
+            ));
+        }
+
+        for entity in stale_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        let mesh = meshes.add(Mesh::from(shape::Icosphere {
+            radius: 1.0,
+            subdivisions: 2,
+        }));
+        for system in universe.systems.iter() {
+            let [r, g, b] = system.color.unwrap_or([1.0, 1.0, 1.0]);
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(r, g, b),
+                        unlit: true,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .insert(NotShadowCaster)
+                .insert(DistantStar {
+                    position: system.position(),
+                    luminosity: system.luminosity,
+                });
+        }
+        commands
+            .spawn()
+            .insert(HudElement::SelectProperty(
+                "universe.destination".into(),
+                universe
+                    .systems
+                    .iter()
+                    .map(|system| {
+                        (
+                            system.name.clone(),
+                            PropertyValue::String(system.name.clone()),
+                        )
+                    })
+                    .collect(),
+            ))
+            .insert(DestinationSelect)
+            .insert(hud_order.next().in_group("3. Travel"));
+    }
+}
+
+fn destination_property_system(
+    mut events: EventReader<PropertyUpdateEvent>,
+    mut universe_state: ResMut<UniverseState>,
+) {
+    for event in events.iter() {
+        if let ("universe.destination", PropertyValue::String(name)) = (event.name(), event.value())
+        {
+            universe_state.destination = Some(name.clone());
+        }
+    }
+}
+
+// J starts a jump to the selected destination. The current system is despawned while in transit
+// and the destination is streamed in on arrival.
+#[allow(clippy::too_many_arguments)]
+fn interstellar_travel_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    universes: Res<Assets<Universe>>,
+    mut universe_state: ResMut<UniverseState>,
+    mut star_system: ResMut<StarSystem>,
+    asset_server: Res<AssetServer>,
+    mut bodies: ResMut<Assets<universe::Body>>,
+    mut ship_query: Query<&mut Velocity, With<ship::Ship>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    let universe = match universes.get(&universe_state.handle) {
+        Some(universe) => universe,
+        None => return,
+    };
+
+    if universe_state.travel.is_none() && keyboard_input.just_pressed(KeyCode::J) {
+        let from = universe_state.position(universe);
+        let to = universe_state
+            .destination
+            .as_ref()
+            .filter(|destination| universe_state.current.as_ref() != Some(*destination))
+            .and_then(|destination| universe.find(destination));
+        if let (Some(from), Some(to)) = (from, to) {
+            info!("jumping to {}", to.name);
+            if let Some(root) = star_system.root.take() {
+                commands.entity(root).despawn_recursive();
+            }
+            star_system.handle = Handle::default();
+            for mut velocity in ship_query.iter_mut() {
+                *velocity = Velocity::default();
+            }
+            universe_state.travel = Some(Travel {
+                from,
+                to: to.name.clone(),
+                progress: 0.0,
+            });
+        }
+    }
+
+    let travel = match universe_state.travel.as_mut() {
+        Some(travel) => travel,
+        None => return,
+    };
+    travel.progress += time.delta_seconds() / TRAVEL_SECONDS;
+    let status = if travel.progress < 1.0 {
+        format!(
+            "in transit to {}, {:.0}%",
+            travel.to,
+            travel.progress * 100.0
+        )
+    } else {
+        let to = universe_state.travel.take().unwrap().to;
+        match universe.find(&to) {
+            Some(entry) => star_system.handle = system_handle(entry, &asset_server, &mut bodies),
+            None => warn!("destination {} vanished from the universe", to),
+        }
+        universe_state.arriving = true;
+        let status = format!("arrived at {}", to);
+        universe_state.current = Some(to);
+        status
+    };
+    property_update_events.send(PropertyUpdateEvent::new(
+        "universe.system".to_string(),
+        PropertyValue::String(status),
+    ));
+}
+
+// keep the distant stars on a sky sphere around the ship, in the direction of their galactic position
+fn distant_star_system(
+    universes: Res<Assets<Universe>>,
+    universe_state: Res<UniverseState>,
+    ship_query: Query<&GlobalTransform, With<ship::Ship>>,
+    mut query: Query<(&DistantStar, &mut Transform, &mut Visibility)>,
+) {
+    let position = match universes
+        .get(&universe_state.handle)
+        .and_then(|universe| universe_state.position(universe))
+    {
+        Some(position) => position,
+        None => return,
+    };
+    let center = ship_query
+        .iter()
+        .next()
+        .map(|transform| transform.translation)
+        .unwrap_or_default();
+    for (star, mut transform, mut visibility) in query.iter_mut() {
+        let offset = star.position - position;
+        let distance = offset.length();
+        // the star of the current system is the real thing
+        visibility.is_visible = distance > 1e-3;
+        if !visibility.is_visible {
             continue;
         }
+        let brightness = galaxy::apparent_brightness(star.luminosity, distance);
+        transform.translation = center + (offset / distance).as_vec3() * SKY_DISTANCE;
+        transform.scale = Vec3::splat(SKY_DISTANCE * 2e-3 * brightness.sqrt().clamp(0.3, 4.0));
+    }
+}
+
+// Every body gets its levels of detail (see lod_system) and the cloud and atmosphere shells of its
+// appearance. Bodies without a model, or whose model failed to load, are drawn procedurally.
+#[allow(clippy::too_many_arguments)]
+fn spawn_planets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scale_mode: Res<ScaleMode>,
+    lod_assets: Res<LodAssets>,
+    loading_status: Res<LoadingStatus>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut query: Query<
+        (Entity, &mut Center, &BodyAppearance),
+        (Without<SpawnPoint>, Without<Barycenter>),
+    >,
+) {
+    for (entity, mut center, appearance) in query.iter_mut() {
         if center.spawned {
             continue;
         }
+        let spec = &appearance.spec;
 
         info!(
-            "spawn {} {} {} {}",
-            center.name, appearance.model, appearance.radius, appearance.vel
+            "spawn {} {:?} {} {}",
+            center.name, spec.model, appearance.radius, appearance.vel
         );
-        let radius = if center.name == "sun" {
-            appearance.radius
+        let procedural = loading_status.is_procedural(spec);
+        if let Some(model) = spec.model_path().filter(|_| procedural) {
+            warn!(
+                "{} failed to load, {} is drawn procedurally",
+                model, center.name
+            );
+        }
+        let model = if procedural {
+            None
         } else {
-            appearance.radius * RADIUS_BOOST
+            model_assets(appearance, &asset_server, &mut materials)
+        };
+        let sphere = Scaled {
+            kind: appearance.kind,
+            radius: appearance.radius,
+            correction: 1.0,
+        };
+        let (part, material, surface) = match model {
+            Some((part, material)) => (
+                part,
+                material,
+                Scaled {
+                    correction: spec.scale,
+                    ..sphere
+                },
+            ),
+            None => {
+                let mut material = appearance.procedural.material(&mut images);
+                // the base color is part of the procedural appearance already
+                let overrides = MaterialSpec {
+                    base_color: None,
+                    ..spec.material.clone()
+                };
+                overrides.apply(&mut material, false);
+                (
+                    ModelPart::Mesh(lod_assets.uv_sphere.clone()),
+                    materials.add(material),
+                    sphere,
+                )
+            }
+        };
+
+        // level, mesh, material, size, translucent
+        let mut levels = vec![
+            // the low-poly sphere wears the material of the model
+            (
+                Lod::Sphere,
+                lod_assets.sphere.clone(),
+                material.clone(),
+                sphere,
+                false,
+            ),
+        ];
+        let scene = match part {
+            ModelPart::Mesh(mesh) => {
+                levels.push((Lod::Full, mesh, material, surface, false));
+                None
+            }
+            ModelPart::Scene(scene) => Some(scene),
         };
+        if let Some(clouds) = &spec.clouds {
+            let texture = format!("models/{}", clouds.texture);
+            let material = StandardMaterial {
+                base_color_texture: Some(asset_server.load(texture.as_str())),
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            };
+            let radius = appearance.radius + clouds.height;
+            let shell = Scaled { radius, ..sphere };
+            let material = materials.add(material);
+            levels.push((
+                Lod::Full,
+                lod_assets.uv_sphere.clone(),
+                material,
+                shell,
+                true,
+            ));
+        }
+        if let Some([r, g, b, a]) = spec.atmosphere {
+            let material = StandardMaterial {
+                base_color: Color::rgba(r, g, b, a),
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 1.0,
+                ..Default::default()
+            };
+            let radius = appearance.radius + appearance.atmosphere_height;
+            let shell = Scaled { radius, ..sphere };
+            let material = materials.add(material);
+            levels.push((
+                Lod::Full,
+                lod_assets.uv_sphere.clone(),
+                material,
+                shell,
+                true,
+            ));
+        }
 
         commands.entity(entity).with_children(|f| {
-            f.spawn_bundle(planet_bundle(
-                &format!("models/{}", appearance.model),
-                radius,
-                &asset_server,
-            ))
-            .insert(Rotation {
-                vel: appearance.vel,
-            });
+            for (lod, mesh, material, scaled, translucent) in levels {
+                let mut level = f.spawn_bundle(PbrBundle {
+                    mesh,
+                    material,
+                    transform: Transform::from_scale(scaled.scale(*scale_mode)),
+                    visibility: Visibility {
+                        is_visible: lod == Lod::Full,
+                    },
+                    ..Default::default()
+                });
+                level.insert(lod).insert(scaled).insert(Rotation {
+                    vel: appearance.vel,
+                    tilt: appearance.axial_tilt,
+                });
+                if translucent {
+                    level.insert(NotShadowCaster);
+                }
+                // light sources neither cast nor receive shadows
+                if appearance.light_source {
+                    level.insert(NotShadowCaster).insert(NotShadowReceiver);
+                }
+            }
+            // the impostor is scaled by lod_system
+            f.spawn_bundle(PbrBundle {
+                mesh: lod_assets.impostor.clone(),
+                material: if appearance.light_source {
+                    lod_assets.star_impostor_material.clone()
+                } else {
+                    lod_assets.impostor_material.clone()
+                },
+                visibility: Visibility { is_visible: false },
+                ..Default::default()
+            })
+            .insert(Lod::Impostor)
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver);
         });
+        if let Some(scene) = scene {
+            let root = commands
+                .spawn_bundle(TransformNodeBundle {
+                    transform: Transform::from_scale(surface.scale(*scale_mode)),
+                    ..Default::default()
+                })
+                .insert(Lod::Full)
+                .insert(surface)
+                .insert(Rotation {
+                    vel: appearance.vel,
+                    tilt: appearance.axial_tilt,
+                })
+                .id();
+            commands.entity(entity).push_children(&[root]);
+            let instance = scene_spawner.spawn_as_child(scene, root);
+            commands.entity(root).insert(SceneRoot {
+                instance,
+                meshes: None,
+            });
+        }
+        commands.entity(entity).insert(LevelOfDetail::default());
         center.spawned = true;
-        break;
     }
 }
 
-fn dump_asset_status(
+// Once the scene of a body is spawned, its meshes get the material overrides and shadow settings of
+// the body's appearance
+fn scene_ready_system(
+    mut commands: Commands,
+    scene_spawner: Res<SceneSpawner>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut root_query: Query<(&mut SceneRoot, &Lod, &Parent)>,
+    appearance_query: Query<(&BodyAppearance, &LevelOfDetail)>,
+    material_query: Query<&Handle<StandardMaterial>>,
+) {
+    for (mut root, lod, parent) in root_query.iter_mut() {
+        if root.meshes.is_some() || !scene_spawner.instance_is_ready(root.instance) {
+            continue;
+        }
+        let (appearance, detail) = match appearance_query.get(parent.0) {
+            Ok(found) => found,
+            Err(_) => continue,
+        };
+        let mut meshes = Vec::new();
+        // meshes sharing a material keep sharing it
+        let mut overridden = HashMap::default();
+        for entity in scene_spawner
+            .iter_instance_entities(root.instance)
+            .into_iter()
+            .flatten()
+        {
+            let material = match material_query.get(entity) {
+                Ok(material) => material.clone(),
+                Err(_) => continue,
+            };
+            let mut mesh = commands.entity(entity);
+            let material = overridden
+                .entry(material.clone())
+                .or_insert_with(|| override_material(appearance, material, &mut materials))
+                .clone();
+            // lod_system only updates the meshes when the level changes
+            mesh.insert(material).insert(Visibility {
+                is_visible: *lod == detail.level,
+            });
+            if appearance.light_source {
+                mesh.insert(NotShadowCaster).insert(NotShadowReceiver);
+            }
+            meshes.push(entity);
+        }
+        root.meshes = Some(meshes);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_belt_rocks(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    scale_mode: Res<ScaleMode>,
     asset_server: Res<AssetServer>,
-    query: Query<&Handle<Mesh>>,
-    query_image: Query<&Handle<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, &BeltRocks), Added<BeltRocks>>,
+    center_query: Query<(&UniversalPosition, &DisplayPosition, &BodyAppearance), With<Center>>,
 ) {
-    // for mesh in query.iter() {
-    //     let path = asset_server.get_handle_path(mesh);
-    //     let state = asset_server.get_load_state(mesh);
-    //     info!("mesh: {:?} {:?}", path, state);
-    // }
+    for (entity, belt) in query.iter() {
+        // one mesh and material per belt, shared by all of its rocks
+        let (mesh, material) = if belt.appearance.is_empty() {
+            (
+                meshes.add(Mesh::from(shape::Icosphere {
+                    radius: 1.0,
+                    subdivisions: 1,
+                })),
+                materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.4, 0.37, 0.33),
+                    perceptual_roughness: 1.0,
+                    ..Default::default()
+                }),
+            )
+        } else {
+            let model = format!("models/{}", belt.appearance);
+            (
+                asset_server.load(&format!("{}#Mesh0/Primitive0", model)),
+                asset_server.load(&format!("{}#Material0", model)),
+            )
+        };
+        let t = clock.days();
+        let (center_position, center_display, center) = match center_query.get(belt.center) {
+            Ok(center) => center,
+            Err(_) => continue,
+        };
+        commands.entity(entity).with_children(|f| {
+            for rock in belt.rocks.iter() {
+                let scaled = Scaled {
+                    kind: BodyKind::Asteroid,
+                    radius: rock.radius,
+                    correction: 1.0,
+                };
+                let offset = orbit_offset(&rock.elements, t);
+                let display = scale_mode.orbit_offset(offset, center.kind, center.radius);
+                f.spawn_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_scale(scaled.scale(*scale_mode)),
+                    ..Default::default()
+                })
+                .insert(Orbit {
+                    elements: rock.elements,
+                    parent: belt.center,
+                })
+                .insert(UniversalPosition(center_position.0 + offset))
+                .insert(DisplayPosition(center_display.0 + display))
+                .insert(scaled)
+                .insert(BeltRock {
+                    name: rock.name.clone(),
+                    radius: rock.radius,
+                });
+            }
+        });
+        info!("spawned {} rocks", belt.rocks.len());
+    }
+}
 
-    // for image in query_image.iter() {
-    //     let path = asset_server.get_handle_path(image);
-    //     let state = asset_server.get_load_state(image);
-    //     info!("image: {:?} {:?}", path, state);
-    // }
+/// rocks closer to the ship than this get a collider
+const ROCK_COLLIDER_RANGE: Length = Length::from_km(1e5);
+
+// Only rocks near the ship take part in physics. The nearest one is the ship's target.
+fn belt_rock_proximity_system(
+    mut commands: Commands,
+    ship_query: Query<&UniversalPosition, With<ship::Ship>>,
+    rock_query: Query<(
+        Entity,
+        &BeltRock,
+        &UniversalPosition,
+        Option<&CollisionShape>,
+    )>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    let ship_position = match ship_query.iter().next() {
+        Some(position) => position,
+        None => return,
+    };
+    let mut target: Option<(&BeltRock, Length)> = None;
+    for (entity, rock, position, collider) in rock_query.iter() {
+        let distance = position.distance(ship_position);
+        if distance < ROCK_COLLIDER_RANGE {
+            if collider.is_none() {
+                commands
+                    .entity(entity)
+                    .insert(RigidBody::KinematicPositionBased)
+                    .insert(CollisionShape::Sphere {
+                        radius: rock.radius.as_units() as f32,
+                    });
+            }
+            if target.map_or(true, |(_, nearest)| distance < nearest) {
+                target = Some((rock, distance));
+            }
+        } else if collider.is_some() && distance > ROCK_COLLIDER_RANGE * 2.0 {
+            commands
+                .entity(entity)
+                .remove::<RigidBody>()
+                .remove::<CollisionShape>();
+        }
+    }
+    let text = match target {
+        Some((rock, distance)) => format!("{} ({})", rock.name, distance),
+        None => String::new(),
+    };
+    property_update_events.send(PropertyUpdateEvent::new(
+        "ship.target".to_string(),
+        PropertyValue::String(text),
+    ));
 }
 
+fn spawn_ship_entity(commands: &mut Commands, transform: Transform) -> Entity {
+    let perspective_projection = PerspectiveProjection {
+        fov: std::f32::consts::PI / 4.0,
+        near: 0.000000001,
+        far: 40.0 * AU_TO_UNIT,
+        aspect_ratio: 1.0,
+    };
+    let elevate = 0.0;
+    commands
+        .spawn_bundle(TransformNodeBundle::default())
+        .insert(transform)
+        .insert(RigidBody::Dynamic)
+        .insert(ship::collision_shape())
+        .insert(Acceleration::default())
+        .insert(Velocity::default())
+        //.insert(Velocity::from_angular(AxisAngle::new(Vec3::X, 1.0)))
+        .insert(ship::Ship {})
+        .insert(UniversalPosition::default())
+        .with_children(|f| {
+            f.spawn_bundle(PerspectiveCameraBundle {
+                // transform: Transform::from_xyz(ORBIT_EARTH, 10e6, 0.0)
+                //     .looking_at(Vec3::new(ORBIT_EARTH, 0.0, 0.0), Vec3::Z),
+                transform: Transform::from_xyz(0.0, elevate, 0.0)
+                    .looking_at(Vec3::new(0.0, elevate, -1.0), Vec3::Y),
+                perspective_projection,
+                ..Default::default()
+            });
+        })
+        .id()
+}
+
+/// Ships of a loaded game, placed once the saved system is spawned, see `spawn_ship`
+#[derive(Default)]
+struct LoadedShips(Option<Vec<ShipState>>);
+
+/// Move the ships to their saved true positions relative to the bodies spawned now (`anchors`),
+/// spawning or despawning ships to match the save game.
+fn place_loaded_ships(
+    commands: &mut Commands,
+    origin: &FloatingOrigin,
+    anchors: &[(DVec3, DVec3)],
+    ships: Vec<ShipState>,
+    ship_query: &mut Query<
+        (Entity, &mut Transform, &mut Velocity, &mut Acceleration),
+        With<ship::Ship>,
+    >,
+) {
+    let render_position =
+        |position| origin.render_position(universal_position::true_to_display(position, anchors));
+    let mut saved_ships = ships.into_iter();
+    for (entity, mut transform, mut velocity, mut acceleration) in ship_query.iter_mut() {
+        match saved_ships.next() {
+            Some(state) => {
+                transform.translation = render_position(state.position);
+                transform.rotation = state.rotation;
+                *velocity = Velocity::from_linear(state.linear_velocity)
+                    .with_angular(state.angular_velocity.into());
+                *acceleration = Acceleration::from_linear(state.linear_acceleration)
+                    .with_angular(state.angular_acceleration.into());
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    for state in saved_ships {
+        let ship = spawn_ship_entity(
+            commands,
+            Transform {
+                translation: render_position(state.position),
+                rotation: state.rotation,
+                ..Default::default()
+            },
+        );
+        commands
+            .entity(ship)
+            .insert(
+                Velocity::from_linear(state.linear_velocity)
+                    .with_angular(state.angular_velocity.into()),
+            )
+            .insert(
+                Acceleration::from_linear(state.linear_acceleration)
+                    .with_angular(state.angular_acceleration.into()),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn spawn_ship(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<(Entity, &Center, &BodyAppearance, &GlobalTransform), Added<Center>>,
+    mut universe_state: ResMut<UniverseState>,
+    mut origin: ResMut<FloatingOrigin>,
+    mut loaded_ships: ResMut<LoadedShips>,
+    query: Query<&DisplayPosition, Added<ShipSpawn>>,
+    anchor_query: Query<(&UniversalPosition, &DisplayPosition)>,
+    mut ship_query: Query<
+        (Entity, &mut Transform, &mut Velocity, &mut Acceleration),
+        With<ship::Ship>,
+    >,
 ) {
-    for (entity, center, appearance, global_transform) in query.iter() {
-        if center.name != "ship" {
+    for position in query.iter() {
+        // the saved system of a loaded game is spawned now, its ships are placed relative to it
+        if let Some(ships) = loaded_ships.0.take() {
+            let anchors: Vec<_> = anchor_query
+                .iter()
+                .map(|(position, display)| (position.0, display.0))
+                .collect();
+            place_loaded_ships(&mut commands, &origin, &anchors, ships, &mut ship_query);
             continue;
         }
-        let perspective_projection = PerspectiveProjection {
-            fov: std::f32::consts::PI / 4.0,
-            near: 0.000000001,
-            far: 40.0 * AU_TO_UNIT,
-            aspect_ratio: 1.0,
-        };
-        let elevate = 0.0;
-        let _ship = commands
-            .spawn_bundle(TransformNodeBundle::default())
-            .insert(Transform {
-                translation: global_transform.translation,
-                ..Default::default()
-            })
-            .insert(RigidBody::Dynamic)
-            .insert(CollisionShape::Cuboid {
-                half_extends: Vec3::new(0.3, 0.3, 0.3),
-                border_radius: Some(0.3),
-            })
-            .insert(Acceleration::default())
-            .insert(Velocity::default())
-            //.insert(Velocity::from_angular(AxisAngle::new(Vec3::X, 1.0)))
-            .insert(ship::Ship {})
-            .with_children(|f| {
-                f.spawn_bundle(PerspectiveCameraBundle {
-                    // transform: Transform::from_xyz(ORBIT_EARTH, 10e6, 0.0)
-                    //     .looking_at(Vec3::new(ORBIT_EARTH, 0.0, 0.0), Vec3::Z),
-                    transform: Transform::from_xyz(0.0, elevate, 0.0)
-                        .looking_at(Vec3::new(0.0, elevate, -1.0), Vec3::Y),
-                    perspective_projection,
-                    ..Default::default()
-                });
+        // the ship survives reloading the star system, don't spawn another one. After an
+        // interstellar jump it is moved to the spawn point of the destination.
+        if let Some((_, mut transform, mut velocity, _)) = ship_query.iter_mut().next() {
+            if universe_state.arriving {
+                origin.offset = position.0;
+                transform.translation = Vec3::ZERO;
+                *velocity = Velocity::default();
+                universe_state.arriving = false;
+            }
+            continue;
+        }
+        // the ship starts at the origin, with the origin moved to the spawn point
+        origin.offset = position.0;
+        let _ship = spawn_ship_entity(&mut commands, Transform::default());
+    }
+}
+
+const SAVE_GAME_PATH: &str = "savegame.yaml";
+
+fn save_game_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    clock: Res<SimClock>,
+    star_system: Res<StarSystem>,
+    universe_state: Res<UniverseState>,
+    bodies: Res<Assets<universe::Body>>,
+    ship_query: Query<(&Transform, &UniversalPosition, &Velocity, &Acceleration), With<ship::Ship>>,
+    property_query: Query<(&PropertyName, &PropertyValue)>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let system = match bodies.get(&star_system.handle) {
+        Some(system) => system.clone(),
+        None => {
+            warn!("cannot save: star system not loaded");
+            return;
+        }
+    };
+
+    let save_game = SaveGame {
+        version: SAVE_GAME_VERSION,
+        clock: ClockState::from(&*clock),
+        system,
+        universe_system: universe_state.current.clone(),
+        ships: ship_query
+            .iter()
+            .map(|(transform, position, velocity, acceleration)| ShipState {
+                position: position.0,
+                rotation: transform.rotation,
+                linear_velocity: velocity.linear,
+                angular_velocity: velocity.angular.into(),
+                linear_acceleration: acceleration.linear,
+                angular_acceleration: acceleration.angular.into(),
             })
-            .id();
+            .collect(),
+        properties: property_query
+            .iter()
+            .filter(|(_, value)| **value != PropertyValue::None)
+            .map(|(name, value)| (name.0.clone(), value.clone()))
+            .collect(),
+    };
+    match save_game.write(std::path::Path::new(SAVE_GAME_PATH)) {
+        Ok(()) => info!("saved game to {}", SAVE_GAME_PATH),
+        Err(err) => error!("{:?}", err),
     }
 }
 
-fn animate_light_direction(
-    time: Res<Time>,
-    mut query: Query<&mut Transform, With<DirectionalLight>>,
+fn load_game_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut clock: ResMut<SimClock>,
+    mut star_system: ResMut<StarSystem>,
+    mut universe_state: ResMut<UniverseState>,
+    mut loaded_ships: ResMut<LoadedShips>,
+    mut bodies: ResMut<Assets<universe::Body>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
 ) {
-    for mut transform in query.iter_mut() {
-        transform.rotation = Quat::from_euler(
-            EulerRot::ZYX,
-            0.0,
-            time.seconds_since_startup() as f32 * std::f32::consts::TAU / 10.0,
-            -std::f32::consts::FRAC_PI_4,
-        );
+    if !keyboard_input.just_pressed(KeyCode::F9) {
+        return;
+    }
+    let save_game = match SaveGame::read(std::path::Path::new(SAVE_GAME_PATH)) {
+        Ok(save_game) => save_game,
+        Err(err) => {
+            error!("{:?}", err);
+            return;
+        }
+    };
+    info!("loading game from {}", SAVE_GAME_PATH);
+
+    save_game.clock.apply(&mut clock);
+    // the new handle triggers AssetEvent::Created, which respawns the Center hierarchy. Saved
+    // positions are true positions, so the ships are placed once the saved bodies are spawned.
+    star_system.handle = bodies.add(save_game.system);
+    loaded_ships.0 = Some(save_game.ships);
+    // a jump in progress is cancelled
+    universe_state.travel = None;
+    universe_state.arriving = false;
+    if let Some(name) = save_game.universe_system {
+        property_update_events.send(PropertyUpdateEvent::new(
+            "universe.system".to_string(),
+            PropertyValue::String(name.clone()),
+        ));
+        universe_state.current = Some(name);
+    }
+
+    for (name, value) in save_game.properties {
+        property_update_events.send(PropertyUpdateEvent::new(name, value));
+    }
+}
+
+// Aim the directional light from the light source lighting the body in view at that body, see
+// `lighting`
+fn star_light_system(
+    scale_mode: Res<ScaleMode>,
+    universe_state: Res<UniverseState>,
+    universes: Res<Assets<Universe>>,
+    camera_query: Query<&GlobalTransform, With<PerspectiveProjection>>,
+    body_query: Query<(&BodyAppearance, &UniversalPosition, &GlobalTransform), With<LevelOfDetail>>,
+    mut light_query: Query<(&mut DirectionalLight, &mut Transform)>,
+) {
+    let camera = match camera_query.iter().next() {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+    // true and render positions
+    let lights: Vec<_> = body_query
+        .iter()
+        .filter(|(appearance, ..)| appearance.light_source)
+        .map(|(_, position, transform)| (position.0, transform.translation))
+        .collect();
+    // the lit body that looks largest from the camera
+    let viewed = body_query
+        .iter()
+        .filter(|(appearance, ..)| !appearance.light_source)
+        .map(|(appearance, position, transform)| {
+            let radius = scale_mode
+                .radius(appearance.kind, appearance.radius)
+                .as_units() as f32;
+            let size = lod::angular_size(radius, transform.translation.distance(camera));
+            (size, radius, position.0, transform.translation)
+        })
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let (_, radius, position, center) = match viewed {
+        Some(viewed) => viewed,
+        None => return,
+    };
+    let (light_position, light_center) =
+        match lighting::brightest(position, lights.iter().map(|(light, _)| *light)) {
+            Some(index) => lights[index],
+            None => return,
+        };
+    let direction = (center - light_center).normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return;
+    }
+    // without a universe the star is the sun
+    let luminosity = universes
+        .get(&universe_state.handle)
+        .zip(universe_state.current.as_ref())
+        .and_then(|(universe, current)| universe.find(current))
+        .map_or(1.0, |system| system.luminosity);
+    let illuminance = lighting::illuminance(
+        Length::from_units(light_position.distance(position)),
+        luminosity,
+    );
+    for (mut light, mut transform) in light_query.iter_mut() {
+        *transform = Transform::identity().looking_at(direction, lighting::up(direction));
+        light.illuminance = illuminance;
+        light.shadow_projection = lighting::shadow_projection(direction, center, radius);
     }
 }
 
@@ -349,18 +1561,148 @@ fn animate_camera(time: Res<Time>, mut query: Query<(&mut Transform, &Frustum),
     }
 }
 
-fn turn_earth(time: Res<Time>, mut query: Query<(&mut Transform, &Planet)>) {
-    for (mut transform, planet) in query.iter_mut() {
-        transform.rotation *= Quat::from_rotation_y(planet.vel * 0.001 * time.delta_seconds());
-        // transform.translation.x += 1000.0;
-        // info!("camera: {:?} {:?}", transform.translation, frustum);
+/// position relative to the parent
+fn orbit_offset(elements: &universe::orbit::OrbitalElements, t: f64) -> DVec3 {
+    // same computation as the headless ephemeris::state_at, one hierarchy level at a time
+    ephemeris::local_state(elements, t).position * AU_TO_UNIT as f64
+}
+
+/// true and displayed position
+fn positions(
+    entity: Entity,
+    t: f64,
+    scale_mode: ScaleMode,
+    orbits: &Query<&Orbit>,
+    appearances: &Query<&BodyAppearance>,
+    cache: &mut HashMap<Entity, (DVec3, DVec3)>,
+) -> (DVec3, DVec3) {
+    if let Some(positions) = cache.get(&entity) {
+        return *positions;
+    }
+    let result = match orbits.get(entity) {
+        Ok(orbit) => {
+            let (parent_position, parent_display) =
+                positions(orbit.parent, t, scale_mode, orbits, appearances, cache);
+            let offset = orbit_offset(&orbit.elements, t);
+            let display = match appearances.get(orbit.parent) {
+                Ok(parent) => scale_mode.orbit_offset(offset, parent.kind, parent.radius),
+                Err(_) => offset,
+            };
+            (parent_position + offset, parent_display + display)
+        }
+        // the star, at the root of the system
+        Err(_) => (DVec3::ZERO, DVec3::ZERO),
+    };
+    cache.insert(entity, result);
+    result
+}
+
+// Parents are resolved recursively, every position is computed once per frame. When the scale mode
+// changes the ships keep their true position, next to the body they were at.
+fn orbit_system(
+    clock: Res<SimClock>,
+    scale_mode: Res<ScaleMode>,
+    mut origin: ResMut<FloatingOrigin>,
+    orbits: Query<&Orbit>,
+    appearances: Query<&BodyAppearance>,
+    mut query: Query<(Entity, &mut UniversalPosition, &mut DisplayPosition), With<Orbit>>,
+    mut ship_query: Query<(&UniversalPosition, &mut Transform), (With<ship::Ship>, Without<Orbit>)>,
+) {
+    let t = clock.days();
+    let mut cache = HashMap::default();
+    for (entity, mut position, mut display) in query.iter_mut() {
+        let (true_position, display_position) =
+            positions(entity, t, *scale_mode, &orbits, &appearances, &mut cache);
+        position.0 = true_position;
+        display.0 = display_position;
+    }
+
+    if !scale_mode.is_changed() || cache.is_empty() {
+        return;
+    }
+    let anchors: Vec<_> = cache.values().copied().collect();
+    for (i, (position, mut transform)) in ship_query.iter_mut().enumerate() {
+        let display = universal_position::true_to_display(position.0, &anchors);
+        if i == 0 {
+            origin.offset = display;
+        }
+        transform.translation = origin.render_position(display);
+    }
+}
+
+// rescale the drawn bodies in place when the scale mode changes
+fn scale_system(scale_mode: Res<ScaleMode>, mut query: Query<(&Scaled, &mut Transform)>) {
+    if !scale_mode.is_changed() {
+        return;
+    }
+    for (scaled, mut transform) in query.iter_mut() {
+        transform.scale = scaled.scale(*scale_mode);
     }
 }
 
-fn rotation_system(time: Res<Time>, mut query: Query<(&mut Transform, &Rotation)>) {
+// show the level of detail matching the apparent size of each body from the camera
+fn lod_system(
+    scale_mode: Res<ScaleMode>,
+    thresholds: Res<LodThresholds>,
+    camera_query: Query<&GlobalTransform, With<PerspectiveProjection>>,
+    mut center_query: Query<(
+        &BodyAppearance,
+        &GlobalTransform,
+        &Children,
+        &mut LevelOfDetail,
+    )>,
+    mut level_query: Query<(&Lod, &mut Visibility, &mut Transform, Option<&SceneRoot>)>,
+    mut scene_mesh_query: Query<&mut Visibility, Without<Lod>>,
+) {
+    let camera = match camera_query.iter().next() {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+    for (appearance, transform, children, mut detail) in center_query.iter_mut() {
+        let distance = transform.translation.distance(camera);
+        let radius = scale_mode
+            .radius(appearance.kind, appearance.radius)
+            .as_units() as f32;
+        let level = detail
+            .level
+            .select(lod::angular_size(radius, distance), &thresholds);
+        // visibility is only written when the level changes, only the impostor follows the camera
+        let changed = level != detail.level || detail.is_added();
+        if !changed && level != Lod::Impostor {
+            continue;
+        }
+        if level != detail.level {
+            detail.level = level;
+        }
+        for child in children.iter() {
+            if let Ok((lod, mut visibility, mut level_transform, scene)) =
+                level_query.get_mut(*child)
+            {
+                if changed {
+                    visibility.is_visible = *lod == level;
+                    // visibility is not inherited, scenes are shown and hidden mesh by mesh. Scenes
+                    // that are not ready yet are set up by scene_ready_system.
+                    let meshes = scene.and_then(|scene| scene.meshes.as_ref());
+                    for mesh in meshes.into_iter().flatten() {
+                        if let Ok(mut visibility) = scene_mesh_query.get_mut(*mesh) {
+                            visibility.is_visible = *lod == level;
+                        }
+                    }
+                }
+                if *lod == Lod::Impostor && level == Lod::Impostor {
+                    level_transform.scale = Vec3::splat(lod::impostor_radius(distance));
+                }
+            }
+        }
+    }
+}
+
+fn rotation_system(clock: Res<SimClock>, mut query: Query<(&mut Transform, &Rotation)>) {
+    // vel is in revolutions per day
+    let days = clock.days();
     for (mut transform, rotation) in query.iter_mut() {
-        transform.rotation *= Quat::from_rotation_y(rotation.vel * 1.0e-1 * time.delta_seconds());
-        // transform.translation.x += 1000.0;
-        // info!("camera: {:?} {:?}", transform.translation, frustum);
+        let angle = (rotation.vel as f64 * days * std::f64::consts::TAU) % std::f64::consts::TAU;
+        transform.rotation =
+            Quat::from_rotation_x(rotation.tilt) * Quat::from_rotation_y(angle as f32);
     }
 }
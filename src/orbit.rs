@@ -0,0 +1,140 @@
+use bevy::math::DVec3;
+use std::f64::consts::TAU;
+
+/// Classical (Keplerian) orbital elements of a body relative to its parent.
+///
+/// Angles are in radians, `semi_major_axis` uses the unit of `Body::orbit` and `period` is in days.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly_at_epoch: f64,
+    pub period: f64,
+}
+
+/// Solve Kepler's equation `M = E - e sin E` for the eccentric anomaly `E` (elliptic orbits only).
+pub fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 32;
+    const EPSILON: f64 = 1e-12;
+
+    let m = mean_anomaly.rem_euclid(TAU);
+    // for high eccentricities newton iteration converges more reliably when starting at pi
+    let mut e_anomaly = if eccentricity > 0.8 {
+        std::f64::consts::PI
+    } else {
+        m
+    };
+    for _ in 0..MAX_ITERATIONS {
+        let delta = (e_anomaly - eccentricity * e_anomaly.sin() - m)
+            / (1.0 - eccentricity * e_anomaly.cos());
+        e_anomaly -= delta;
+        if delta.abs() < EPSILON {
+            break;
+        }
+    }
+    e_anomaly
+}
+
+impl OrbitalElements {
+    pub fn circular(radius: f64, period: f64) -> Self {
+        OrbitalElements {
+            semi_major_axis: radius,
+            period,
+            ..Default::default()
+        }
+    }
+
+    /// mean motion in radians per day (zero for bodies without an orbital period)
+    pub fn mean_motion(&self) -> f64 {
+        if self.period > 0.0 {
+            TAU / self.period
+        } else {
+            0.0
+        }
+    }
+
+    pub fn mean_anomaly(&self, t: f64) -> f64 {
+        self.mean_anomaly_at_epoch + self.mean_motion() * t
+    }
+
+    pub fn eccentric_anomaly(&self, t: f64) -> f64 {
+        solve_kepler(self.mean_anomaly(t), self.eccentricity)
+    }
+
+    /// Position relative to the parent body at time `t` (days since epoch).
+    pub fn position(&self, t: f64) -> DVec3 {
+        let e_anomaly = self.eccentric_anomaly(t);
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let x = a * (e_anomaly.cos() - e);
+        let y = a * (1.0 - e * e).sqrt() * e_anomaly.sin();
+        self.perifocal_to_world(x, y)
+    }
+
+    /// Velocity relative to the parent body at time `t` (unit of `semi_major_axis` per day).
+    pub fn velocity(&self, t: f64) -> DVec3 {
+        let e_anomaly = self.eccentric_anomaly(t);
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let e_anomaly_dot = self.mean_motion() / (1.0 - e * e_anomaly.cos());
+        let x = -a * e_anomaly.sin() * e_anomaly_dot;
+        let y = a * (1.0 - e * e).sqrt() * e_anomaly.cos() * e_anomaly_dot;
+        self.perifocal_to_world(x, y)
+    }
+
+    /// Rotate a vector from the perifocal frame (x towards periapsis, y along the direction of motion)
+    /// into the parent's frame.
+    ///
+    /// The reference plane is the bevy XZ plane with the reference direction along +Z, so a circular
+    /// equatorial orbit starts on the +Z axis and moves towards +X (matching a rotation around +Y).
+    pub(crate) fn perifocal_to_world(&self, x: f64, y: f64) -> DVec3 {
+        let (sin_o, cos_o) = self.longitude_of_ascending_node.sin_cos();
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+
+        // standard perifocal -> reference frame rotation Rz(O) * Rx(i) * Rz(w)
+        let rx = (cos_o * cos_w - sin_o * sin_w * cos_i) * x
+            + (-cos_o * sin_w - sin_o * cos_w * cos_i) * y;
+        let ry = (sin_o * cos_w + cos_o * sin_w * cos_i) * x
+            + (-sin_o * sin_w + cos_o * cos_w * cos_i) * y;
+        let rz = (sin_w * sin_i) * x + (cos_w * sin_i) * y;
+
+        // reference frame (x, y, z-north) -> bevy (y, z-north, x)
+        DVec3::new(ry, rz, rx)
+    }
+}
+
+#[test]
+fn test_kepler() {
+    for e in [0.0, 0.1, 0.5, 0.9, 0.99] {
+        for i in 0..16 {
+            let m = i as f64 * TAU / 16.0;
+            let e_anomaly = solve_kepler(m, e);
+            let residual = e_anomaly - e * e_anomaly.sin() - m;
+            assert!(
+                (residual.rem_euclid(TAU)).min(TAU - residual.rem_euclid(TAU)) < 1e-9,
+                "e={} m={} residual={}",
+                e,
+                m,
+                residual
+            );
+        }
+    }
+
+    let circular = OrbitalElements::circular(2.0, 10.0);
+    assert!((circular.position(0.0) - DVec3::new(0.0, 0.0, 2.0)).length() < 1e-9);
+    assert!((circular.position(2.5) - DVec3::new(2.0, 0.0, 0.0)).length() < 1e-9);
+
+    let eccentric = OrbitalElements {
+        semi_major_axis: 1.0,
+        eccentricity: 0.5,
+        period: 1.0,
+        ..Default::default()
+    };
+    // periapsis at epoch, apoapsis after half a period
+    assert!((eccentric.position(0.0).length() - 0.5).abs() < 1e-9);
+    assert!((eccentric.position(0.5).length() - 1.5).abs() < 1e-9);
+}
@@ -0,0 +1,95 @@
+//! Quantities derived from the physical properties of a `Body`.
+use crate::{
+    quantity::{Duration, Length, Mass, Velocity},
+    Body,
+};
+
+/// gravitational constant in km^3 / (kg s^2)
+pub const G: f64 = 6.674_30e-20;
+
+impl Body {
+    /// standard gravitational parameter in km^3/s^2
+    pub fn gm(&self) -> Option<f64> {
+        self.gm.or_else(|| self.mass.map(|mass| G * mass.as_kg()))
+    }
+
+    pub fn mass(&self) -> Option<Mass> {
+        self.mass
+            .or_else(|| self.gm.map(|gm| Mass::from_kg(gm / G)))
+    }
+
+    /// sidereal rotation period (zero or negative: no rotation)
+    pub fn rotation_period(&self) -> Duration {
+        self.rotation_period.unwrap_or(self.day)
+    }
+
+    /// m/s^2
+    pub fn surface_gravity(&self) -> Option<f64> {
+        if self.radius <= Length::ZERO {
+            return None;
+        }
+        let radius = self.radius.as_km();
+        Some(self.gm()? / (radius * radius) * 1e3)
+    }
+
+    pub fn escape_velocity(&self) -> Option<Velocity> {
+        if self.radius <= Length::ZERO {
+            return None;
+        }
+        let km_per_s = (2.0 * self.gm()? / self.radius.as_km()).sqrt();
+        Some(Velocity::from_km_per_s(km_per_s))
+    }
+
+    /// Hill sphere radius, for a body orbiting `parent`
+    pub fn hill_sphere(&self, parent: &Body) -> Option<Length> {
+        let ratio = self.gm()? / (3.0 * parent.gm()?);
+        Some(self.orbit * (1.0 - self.eccentricity as f64) * ratio.cbrt())
+    }
+
+    /// Laplace sphere of influence, for a body orbiting `parent`: inside it the body's gravity
+    /// dominates the motion (see `soi`)
+    pub fn sphere_of_influence(&self, parent: &Body) -> Option<Length> {
+        let ratio = self.gm()? / parent.gm()?;
+        Some(self.orbit * ratio.powf(0.4))
+    }
+
+    /// orbital period from Kepler's third law, for a body orbiting `parent`
+    pub fn kepler_period(&self, parent: &Body) -> Option<Duration> {
+        let gm = parent.gm()? + self.gm().unwrap_or(0.0);
+        Some(orbital_period(self.orbit, gm))
+    }
+}
+
+/// orbital period for semi-major axis `a` around a central body with `gm` (km^3/s^2)
+pub fn orbital_period(a: Length, gm: f64) -> Duration {
+    let a = a.as_km();
+    Duration::from_seconds(std::f64::consts::TAU * (a * a * a / gm).sqrt())
+}
+
+#[test]
+fn test_derived_quantities() {
+    let sun = Body {
+        name: "sun".into(),
+        gm: Some(1.327_124_400_18e11),
+        ..Default::default()
+    };
+    let earth = Body {
+        name: "earth".into(),
+        orbit: Length::from_au(1.0),
+        radius: Length::from_km(6371.0),
+        mass: Some(Mass::from_kg(5.9722e24)),
+        ..Default::default()
+    };
+
+    assert!((earth.surface_gravity().unwrap() - 9.82).abs() < 0.01);
+    assert!((earth.escape_velocity().unwrap().as_km_per_s() - 11.19).abs() < 0.01);
+    // ~1.5 million km
+    assert!((earth.hill_sphere(&sun).unwrap().as_km() - 1.496e6).abs() < 0.01e6);
+    // ~925 thousand km
+    assert!((earth.sphere_of_influence(&sun).unwrap().as_km() - 9.25e5).abs() < 0.01e6);
+    assert!((earth.kepler_period(&sun).unwrap().as_days() - 365.25).abs() < 0.1);
+    assert!((sun.mass().unwrap().as_kg() - 1.989e30).abs() < 0.001e30);
+    // all the digits of a JPL GM are kept
+    assert_eq!(sun.gm(), Some(132_712_440_018.0));
+    assert_eq!(sun.surface_gravity(), None);
+}
@@ -0,0 +1,211 @@
+//! Procedural appearances.
+//!
+//! Bodies without a model (`appearance: none`, or a model that failed to load) are drawn as a UV
+//! sphere with a base color. Stars glow, rocky bodies get a surface texture of fractal value noise
+//! generated on the CPU. Everything is derived from the body's kind and name, so a body looks the
+//! same every time it is spawned.
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{Body, BodyKind};
+
+pub const TEXTURE_WIDTH: u32 = 256;
+pub const TEXTURE_HEIGHT: u32 = 128;
+const OCTAVES: u32 = 4;
+/// lattice cells per unit of the sphere's radius, for the first octave
+const FREQUENCY: f32 = 3.0;
+
+const STAR_COLOR: [f32; 3] = [1.0, 0.85, 0.55];
+const STATION_COLOR: [f32; 3] = [0.6, 0.6, 0.65];
+const SURFACE_COLORS: [[f32; 3]; 5] = [
+    [0.55, 0.5, 0.45],
+    [0.7, 0.45, 0.3],
+    [0.45, 0.5, 0.6],
+    [0.8, 0.75, 0.6],
+    [0.35, 0.45, 0.3],
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProceduralAppearance {
+    /// sRGB
+    pub base_color: [f32; 3],
+    pub emissive: bool,
+    /// seed of the surface texture, None for a plain color
+    pub noise_seed: Option<u64>,
+}
+
+/// FNV-1a, stable across platforms and releases unlike the std hashers
+fn name_seed(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// pseudo-random value in [0, 1) for a lattice point
+fn lattice(seed: u64, x: i32, y: i32, z: i32) -> f32 {
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    // splitmix64 finalizer
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^= h >> 31;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn value_noise(seed: u64, p: Vec3) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    // smoothstep, so the noise has no creases at cell borders
+    let w = f * f * (Vec3::splat(3.0) - 2.0 * f);
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let corner = |dx, dy, dz| lattice(seed, x + dx, y + dy, z + dz);
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), w.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), w.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), w.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), w.x);
+    lerp(lerp(x00, x10, w.y), lerp(x01, x11, w.y), w.z)
+}
+
+/// fractal value noise in [0, 1]
+fn fractal_noise(seed: u64, p: Vec3) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    for octave in 0..OCTAVES {
+        let frequency = (1 << octave) as f32;
+        sum += amplitude * value_noise(seed.wrapping_add(octave as u64), p * frequency);
+        total += amplitude;
+        amplitude *= 0.5;
+    }
+    sum / total
+}
+
+impl ProceduralAppearance {
+    /// The look of `body`, colors given in its appearance take precedence
+    pub fn for_body(body: &Body) -> Self {
+        let seed = name_seed(&body.name);
+        let mut appearance = match body.kind {
+            BodyKind::Star => ProceduralAppearance {
+                base_color: STAR_COLOR,
+                emissive: true,
+                noise_seed: None,
+            },
+            BodyKind::Station => ProceduralAppearance {
+                base_color: STATION_COLOR,
+                emissive: false,
+                noise_seed: None,
+            },
+            _ => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let base = SURFACE_COLORS[rng.gen_range(0..SURFACE_COLORS.len())];
+                let tint = rng.gen_range(0.85..1.15);
+                ProceduralAppearance {
+                    base_color: base.map(|c| (c * tint).min(1.0)),
+                    emissive: false,
+                    noise_seed: Some(seed),
+                }
+            }
+        };
+        if let Some(base_color) = body.appearance.material.base_color {
+            appearance.base_color = base_color;
+        }
+        appearance.emissive = body.appearance.is_light_source(body.kind);
+        appearance
+    }
+
+    /// Equirectangular RGBA8 (sRGB) surface texture, rows from north to south, or None for a plain
+    /// color. The noise is sampled on the sphere, so there is no seam and no pinching at the poles.
+    pub fn texture(&self, width: u32, height: u32) -> Option<Vec<u8>> {
+        let seed = self.noise_seed?;
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for row in 0..height {
+            let latitude = std::f32::consts::PI * (0.5 - (row as f32 + 0.5) / height as f32);
+            for column in 0..width {
+                let longitude = std::f32::consts::TAU * (column as f32 + 0.5) / width as f32;
+                let p = Vec3::new(
+                    latitude.cos() * longitude.cos(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.sin(),
+                );
+                let shade = 0.4 + 0.8 * fractal_noise(seed, p * FREQUENCY);
+                for c in self.base_color {
+                    data.push(((c * shade).clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+                data.push(255);
+            }
+        }
+        Some(data)
+    }
+
+    pub fn material(&self, images: &mut Assets<Image>) -> StandardMaterial {
+        let [r, g, b] = self.base_color;
+        let color = Color::rgb(r, g, b);
+        let texture = self.texture(TEXTURE_WIDTH, TEXTURE_HEIGHT).map(|data| {
+            images.add(Image::new(
+                Extent3d {
+                    width: TEXTURE_WIDTH,
+                    height: TEXTURE_HEIGHT,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8UnormSrgb,
+            ))
+        });
+        StandardMaterial {
+            // the texture carries the color
+            base_color: if texture.is_some() {
+                Color::WHITE
+            } else {
+                color
+            },
+            base_color_texture: texture,
+            emissive: if self.emissive { color } else { Color::BLACK },
+            perceptual_roughness: 0.9,
+            ..Default::default()
+        }
+    }
+}
+
+/// UV sphere of radius 1, the texture coordinates match `ProceduralAppearance::texture`
+pub fn sphere_mesh() -> Mesh {
+    Mesh::from(shape::UVSphere {
+        radius: 1.0,
+        sectors: 48,
+        stacks: 24,
+    })
+}
+
+#[test]
+fn test_procedural_appearance() {
+    let body = |name: &str, kind| Body {
+        name: name.into(),
+        kind,
+        ..Default::default()
+    };
+    let moon = ProceduralAppearance::for_body(&body("moon", BodyKind::Moon));
+    assert_eq!(
+        moon,
+        ProceduralAppearance::for_body(&body("moon", BodyKind::Moon))
+    );
+    let texture = moon.texture(64, 32).unwrap();
+    assert_eq!(texture.len(), 64 * 32 * 4);
+    assert_eq!(texture, moon.texture(64, 32).unwrap());
+    // there is some surface detail
+    let reds: Vec<u8> = texture.iter().step_by(4).copied().collect();
+    assert!(reds.iter().max().unwrap() - reds.iter().min().unwrap() > 20);
+
+    let other = ProceduralAppearance::for_body(&body("phobos", BodyKind::Moon));
+    assert_ne!(other.texture(64, 32).unwrap(), texture);
+
+    let star = ProceduralAppearance::for_body(&body("sun", BodyKind::Star));
+    assert!(star.emissive);
+    assert_eq!(star.texture(64, 32), None);
+}
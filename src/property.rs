@@ -1,13 +1,16 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     sync::Mutex,
 };
 
+use crate::quantity::{Duration, Length, Velocity};
+
 #[derive(Debug, Clone, Component)]
 pub struct PropertyName(pub String);
 
-#[derive(Clone, Debug, PartialEq, Component)]
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
 pub enum PropertyValue {
     None,
     Bool(bool),
@@ -15,6 +18,9 @@ pub enum PropertyValue {
     Color(Vec3),
     Vec3(Vec3),
     Float(f32),
+    Length(Length),
+    Velocity(Velocity),
+    Duration(Duration),
 }
 
 #[derive(Debug)]
@@ -27,6 +33,12 @@ impl PropertyUpdateEvent {
     pub fn new(name: String, value: PropertyValue) -> Self {
         PropertyUpdateEvent { name, value }
     }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &PropertyValue {
+        &self.value
+    }
 }
 
 #[derive(Component)]
@@ -0,0 +1,327 @@
+//! Typed quantities.
+//!
+//! Lengths, durations, velocities, masses and angles each get their own type. The value is stored in
+//! the base unit of the matching `units::Dimension` and only available through explicitly named
+//! constructors and accessors, so passing km where AU (or render units) are expected does not
+//! compile. `Display` picks a readable unit, for the HUD and for messages. Serialized quantities
+//! (e.g. in properties) are bare numbers in the base unit, `units` handles fields of system files.
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{consts::AU_TO_UNIT, units};
+
+pub const KM_PER_AU: f64 = 1.495_978_707e8;
+pub const SECONDS_PER_DAY: f64 = 86400.0;
+/// render units per km, `consts::KM_TO_UNIT` in full precision
+const UNITS_PER_KM: f64 = AU_TO_UNIT as f64 / KM_PER_AU;
+
+/// A quantity stored in the base unit of its dimension, used by the unit-aware (de)serializers
+pub trait Quantity: Copy {
+    const DIMENSION: &'static units::Dimension;
+    fn from_base(value: f64) -> Self;
+    fn base(self) -> f64;
+}
+
+macro_rules! quantity {
+    ($name:ident, $dimension:expr) => {
+        impl Quantity for $name {
+            const DIMENSION: &'static units::Dimension = &$dimension;
+            fn from_base(value: f64) -> Self {
+                $name(value)
+            }
+            fn base(self) -> f64 {
+                self.0
+            }
+        }
+        quantity!($name);
+    };
+    ($name:ident) => {
+        impl $name {
+            pub const ZERO: $name = $name(0.0);
+
+            pub fn abs(self) -> Self {
+                $name(self.0.abs())
+            }
+
+            pub fn min(self, other: Self) -> Self {
+                $name(self.0.min(other.0))
+            }
+
+            pub fn max(self, other: Self) -> Self {
+                $name(self.0.max(other.0))
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: $name) {
+                self.0 += other.0;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+            fn sub(self, other: $name) -> $name {
+                $name(self.0 - other.0)
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: $name) {
+                self.0 -= other.0;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = $name;
+            fn mul(self, factor: f64) -> $name {
+                $name(self.0 * factor)
+            }
+        }
+
+        impl MulAssign<f64> for $name {
+            fn mul_assign(&mut self, factor: f64) {
+                self.0 *= factor;
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = $name;
+            fn div(self, divisor: f64) -> $name {
+                $name(self.0 / divisor)
+            }
+        }
+
+        /// ratio of two quantities of the same kind
+        impl Div for $name {
+            type Output = f64;
+            fn div(self, other: $name) -> f64 {
+                self.0 / other.0
+            }
+        }
+    };
+}
+
+/// base unit: km
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Length(f64);
+quantity!(Length, units::LENGTH);
+
+impl Length {
+    pub fn from_meters(meters: f64) -> Self {
+        Length(meters * 1e-3)
+    }
+    pub const fn from_km(km: f64) -> Self {
+        Length(km)
+    }
+    pub fn from_au(au: f64) -> Self {
+        Length(au * KM_PER_AU)
+    }
+    /// from render units
+    pub fn from_units(units: f64) -> Self {
+        Length(units / UNITS_PER_KM)
+    }
+
+    pub fn as_meters(self) -> f64 {
+        self.0 * 1e3
+    }
+    pub fn as_km(self) -> f64 {
+        self.0
+    }
+    pub fn as_au(self) -> f64 {
+        self.0 / KM_PER_AU
+    }
+    /// in render units
+    pub fn as_units(self) -> f64 {
+        self.0 * UNITS_PER_KM
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let km = self.0.abs();
+        if km < 1.0 {
+            write!(f, "{:.0} m", self.as_meters())
+        } else if km < 0.01 * KM_PER_AU {
+            write!(f, "{:.0} km", self.0)
+        } else {
+            write!(f, "{:.3} AU", self.as_au())
+        }
+    }
+}
+
+/// base unit: day
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Duration(f64);
+quantity!(Duration, units::DURATION);
+
+impl Duration {
+    pub fn from_seconds(seconds: f64) -> Self {
+        Duration(seconds / SECONDS_PER_DAY)
+    }
+    pub fn from_hours(hours: f64) -> Self {
+        Duration(hours / 24.0)
+    }
+    pub const fn from_days(days: f64) -> Self {
+        Duration(days)
+    }
+
+    pub fn as_seconds(self) -> f64 {
+        self.0 * SECONDS_PER_DAY
+    }
+    pub fn as_hours(self) -> f64 {
+        self.0 * 24.0
+    }
+    pub fn as_days(self) -> f64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0.abs();
+        if days < 1.0 / 24.0 {
+            write!(f, "{:.0} s", self.as_seconds())
+        } else if days < 1.0 {
+            write!(f, "{:.1} h", self.as_hours())
+        } else if days < 365.25 {
+            write!(f, "{:.2} d", self.0)
+        } else {
+            write!(f, "{:.2} y", self.0 / 365.25)
+        }
+    }
+}
+
+/// base unit: km/s
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Velocity(f64);
+quantity!(Velocity);
+
+impl Velocity {
+    pub const fn from_km_per_s(km_per_s: f64) -> Self {
+        Velocity(km_per_s)
+    }
+    /// from render units per second
+    pub fn from_units_per_s(units_per_s: f64) -> Self {
+        Velocity(Length::from_units(units_per_s).as_km())
+    }
+
+    pub fn as_m_per_s(self) -> f64 {
+        self.0 * 1e3
+    }
+    pub fn as_km_per_s(self) -> f64 {
+        self.0
+    }
+    /// in render units per second
+    pub fn as_units_per_s(self) -> f64 {
+        Length::from_km(self.0).as_units()
+    }
+}
+
+impl Div<Duration> for Length {
+    type Output = Velocity;
+    fn div(self, duration: Duration) -> Velocity {
+        Velocity(self.as_km() / duration.as_seconds())
+    }
+}
+
+impl Mul<Duration> for Velocity {
+    type Output = Length;
+    fn mul(self, duration: Duration) -> Length {
+        Length::from_km(self.0 * duration.as_seconds())
+    }
+}
+
+impl fmt::Display for Velocity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.abs() < 1.0 {
+            write!(f, "{:.1} m/s", self.as_m_per_s())
+        } else {
+            write!(f, "{:.3} km/s", self.0)
+        }
+    }
+}
+
+/// base unit: kg
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Mass(f64);
+quantity!(Mass, units::MASS);
+
+impl Mass {
+    pub const fn from_kg(kg: f64) -> Self {
+        Mass(kg)
+    }
+
+    pub fn as_kg(self) -> f64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Mass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3e} kg", self.0)
+    }
+}
+
+/// base unit: degree
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Angle(f64);
+quantity!(Angle, units::ANGLE);
+
+impl Angle {
+    pub const fn from_degrees(degrees: f64) -> Self {
+        Angle(degrees)
+    }
+    pub fn from_radians(radians: f64) -> Self {
+        Angle(radians.to_degrees())
+    }
+
+    pub fn as_degrees(self) -> f64 {
+        self.0
+    }
+    pub fn as_radians(self) -> f64 {
+        self.0.to_radians()
+    }
+}
+
+impl fmt::Display for Angle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}°", self.0)
+    }
+}
+
+#[test]
+fn test_quantities() {
+    let moon = Length::from_km(384_400.0);
+    assert!((moon.as_au() - 0.00257).abs() < 1e-5);
+    assert!((Length::from_units(moon.as_units()).as_km() - 384_400.0).abs() < 1e-6);
+    assert_eq!(moon.to_string(), "384400 km");
+    assert_eq!(Length::from_au(5.2).to_string(), "5.200 AU");
+
+    let speed = Length::from_km(7.5) / Duration::from_seconds(1.0);
+    assert_eq!(speed, Velocity::from_km_per_s(7.5));
+    assert_eq!(speed * Duration::from_seconds(2.0), Length::from_km(15.0));
+    assert_eq!(Velocity::from_km_per_s(0.25).to_string(), "250.0 m/s");
+
+    assert_eq!(Duration::from_hours(36.0), Duration::from_days(1.5));
+    assert_eq!(Duration::from_days(27.3).to_string(), "27.30 d");
+    assert!((Angle::from_radians(std::f64::consts::PI).as_degrees() - 180.0).abs() < 1e-12);
+    assert_eq!(Length::from_au(2.0) / Length::from_au(1.0), 2.0);
+}
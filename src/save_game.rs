@@ -0,0 +1,141 @@
+//! Versioned snapshot of the live simulation state.
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, Context};
+use bevy::{math::DVec3, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{property::PropertyValue, sim_clock::SimClock, Body};
+
+/// bump whenever the layout of `SaveGame` changes incompatibly
+pub const SAVE_GAME_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClockState {
+    pub epoch: f64,
+    pub time: f64,
+    pub warp: f64,
+    pub paused: bool,
+}
+
+impl From<&SimClock> for ClockState {
+    fn from(clock: &SimClock) -> Self {
+        ClockState {
+            epoch: clock.epoch,
+            time: clock.time,
+            warp: clock.warp,
+            paused: clock.paused,
+        }
+    }
+}
+
+impl ClockState {
+    pub fn apply(&self, clock: &mut SimClock) {
+        clock.epoch = self.epoch;
+        clock.time = self.time;
+        clock.warp = self.warp;
+        clock.paused = self.paused;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShipState {
+    /// relative to the root of the star system
+    pub position: DVec3,
+    pub rotation: Quat,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub linear_acceleration: Vec3,
+    pub angular_acceleration: Vec3,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveGame {
+    pub version: u32,
+    pub clock: ClockState,
+    pub system: Body,
+    /// the system of the universe the game was saved in, see `galaxy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub universe_system: Option<String>,
+    pub ships: Vec<ShipState>,
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+impl SaveGame {
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create save game {:?}", path))?;
+        serde_yaml::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<SaveGame> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open save game {:?}", path))?;
+        let save_game: SaveGame = serde_yaml::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("failed to parse save game {:?}", path))?;
+        if save_game.version != SAVE_GAME_VERSION {
+            bail!(
+                "unsupported save game version {} in {:?} (expected {})",
+                save_game.version,
+                path,
+                SAVE_GAME_VERSION
+            );
+        }
+        Ok(save_game)
+    }
+}
+
+#[test]
+fn test_save_game_roundtrip() {
+    let mut properties = BTreeMap::new();
+    properties.insert("sim.warp".to_string(), PropertyValue::Float(1e3));
+    properties.insert(
+        "ship.position".to_string(),
+        PropertyValue::Vec3(Vec3::new(1.0, 2.0, 3.0)),
+    );
+    let save_game = SaveGame {
+        version: SAVE_GAME_VERSION,
+        clock: ClockState {
+            epoch: crate::sim_clock::J2000,
+            time: 123456.789,
+            warp: 1e3,
+            paused: true,
+        },
+        system: Body {
+            name: "sun".into(),
+            satellites: vec![Body {
+                name: "earth".into(),
+                orbit: crate::quantity::Length::from_km(149_598_023.0),
+                orbit_time: crate::quantity::Duration::from_days(365.256_363_004),
+                eccentricity: 0.016_708_6,
+                inclination: crate::quantity::Angle::from_degrees(0.000_05),
+                gm: Some(398_600.441_8),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        universe_system: Some("sol".into()),
+        ships: vec![ShipState {
+            position: DVec3::new(30.0, 0.0, 1e-12),
+            rotation: Quat::from_rotation_y(0.5),
+            linear_velocity: Vec3::new(0.0, 1e-9, 0.0),
+            angular_velocity: Vec3::ZERO,
+            linear_acceleration: Vec3::ZERO,
+            angular_acceleration: Vec3::X,
+        }],
+        properties,
+    };
+
+    let path = std::env::temp_dir().join("universe_test_save_game.yaml");
+    save_game.write(&path).unwrap();
+    let loaded = SaveGame::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.clock, save_game.clock);
+    assert_eq!(loaded.universe_system, save_game.universe_system);
+    assert_eq!(loaded.ships, save_game.ships);
+    assert_eq!(loaded.properties, save_game.properties);
+    // exactly, not just the same lossy text
+    assert_eq!(loaded.system, save_game.system);
+}
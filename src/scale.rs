@@ -0,0 +1,176 @@
+//! Scale modes for viewing star systems.
+//!
+//! At true scale planets are invisible specks except up close. The scale modes change how bodies are
+//! drawn: their radii and the distances to their parents. They only affect rendering. Positions
+//! used by orbits, readouts and saves stay true, and so does ship physics.
+use bevy::{math::DVec3, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    property::{PropertyUpdateEvent, PropertyValue},
+    quantity::Length,
+    BodyKind,
+};
+
+const EARTH_RADIUS: Length = Length::from_km(6_371.0);
+const SUN_RADIUS: Length = Length::from_km(696_000.0);
+/// radius multiplier for non-stars in `ExaggeratedRadii`
+const EXAGGERATION: f64 = 50.0;
+/// orbits around stars are shrunk by this in `CompressedOrbits`
+const COMPRESSION: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleMode {
+    Realistic,
+    /// planets and moons are drawn larger, moon orbits grow along so moons stay outside
+    ExaggeratedRadii,
+    /// orbits around stars are shrunk
+    CompressedOrbits,
+    /// logarithmic distances and square-root radii, everything fits into one view
+    Orrery,
+}
+
+impl Default for ScaleMode {
+    fn default() -> Self {
+        ScaleMode::Realistic
+    }
+}
+
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 4] = [
+        ScaleMode::Realistic,
+        ScaleMode::ExaggeratedRadii,
+        ScaleMode::CompressedOrbits,
+        ScaleMode::Orrery,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScaleMode::Realistic => "realistic",
+            ScaleMode::ExaggeratedRadii => "exaggerated radii",
+            ScaleMode::CompressedOrbits => "compressed orbits",
+            ScaleMode::Orrery => "orrery",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ScaleMode> {
+        ScaleMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name() == name)
+    }
+
+    /// Drawn radius of a body of `kind` with true `radius`
+    pub fn radius(&self, kind: BodyKind, radius: Length) -> Length {
+        let star = kind == BodyKind::Star;
+        match self {
+            ScaleMode::Realistic | ScaleMode::CompressedOrbits => radius,
+            ScaleMode::ExaggeratedRadii if star => radius,
+            ScaleMode::ExaggeratedRadii => radius * EXAGGERATION,
+            ScaleMode::Orrery if star => Length::from_au(0.2) * (radius / SUN_RADIUS).sqrt(),
+            ScaleMode::Orrery => Length::from_au(0.02) * (radius / EARTH_RADIUS).sqrt(),
+        }
+    }
+
+    /// Drawn offset of a body from its parent, for the true `offset` (render units). The parent's
+    /// kind and true radius keep satellites outside of their (scaled) parent.
+    pub fn orbit_offset(
+        &self,
+        offset: DVec3,
+        parent_kind: BodyKind,
+        parent_radius: Length,
+    ) -> DVec3 {
+        let around_star = parent_kind == BodyKind::Star;
+        match self {
+            ScaleMode::Realistic => offset,
+            ScaleMode::ExaggeratedRadii if around_star => offset,
+            ScaleMode::ExaggeratedRadii => offset * EXAGGERATION,
+            ScaleMode::CompressedOrbits if around_star => offset * COMPRESSION,
+            ScaleMode::CompressedOrbits => offset,
+            ScaleMode::Orrery => {
+                let distance = Length::from_units(offset.length());
+                if distance == Length::ZERO {
+                    return offset;
+                }
+                // reference distance and step per doubling
+                let (reference, step) = if around_star {
+                    (Length::from_au(0.1), Length::from_au(1.0))
+                } else {
+                    (Length::from_au(1e-3), Length::from_au(0.05))
+                };
+                let scaled = self.radius(parent_kind, parent_radius)
+                    + step * (1.0 + distance / reference).log2();
+                offset * (scaled / distance)
+            }
+        }
+    }
+}
+
+// publish the mode at startup and whenever it changes, so the hud shows the active one
+pub fn update_properties_system(
+    scale_mode: Res<ScaleMode>,
+    mut published: Local<Option<ScaleMode>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    if *published != Some(*scale_mode) {
+        *published = Some(*scale_mode);
+        property_update_events.send(PropertyUpdateEvent::new(
+            "view.scale".to_string(),
+            PropertyValue::String(scale_mode.name().into()),
+        ));
+    }
+}
+
+// the mode is selected in the hud, through the view.scale property
+pub fn apply_property_updates_system(
+    mut events: EventReader<PropertyUpdateEvent>,
+    mut scale_mode: ResMut<ScaleMode>,
+) {
+    for event in events.iter() {
+        if let ("view.scale", PropertyValue::String(name)) = (event.name(), event.value()) {
+            match ScaleMode::from_name(name) {
+                Some(mode) if mode != *scale_mode => *scale_mode = mode,
+                Some(_) => (),
+                None => warn!("unknown scale mode {}", name),
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScalePlugin;
+
+impl Plugin for ScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScaleMode>()
+            .add_system(update_properties_system.system())
+            .add_system(apply_property_updates_system.system());
+    }
+}
+
+#[test]
+fn test_scale_modes() {
+    let mars_radius = Length::from_km(3_389.5);
+    let phobos = DVec3::new(0.0, 0.0, Length::from_km(9_376.0).as_units());
+    let neptune = DVec3::new(Length::from_au(30.07).as_units(), 0.0, 0.0);
+
+    for mode in ScaleMode::ALL {
+        assert_eq!(ScaleMode::from_name(mode.name()), Some(mode));
+        // satellites stay outside of their parent
+        let offset = mode.orbit_offset(phobos, BodyKind::Planet, mars_radius);
+        assert!(offset.length() > mode.radius(BodyKind::Planet, mars_radius).as_units());
+        assert!(offset.normalize().abs_diff_eq(phobos.normalize(), 1e-12));
+    }
+
+    assert_eq!(
+        ScaleMode::Realistic.orbit_offset(neptune, BodyKind::Star, SUN_RADIUS),
+        neptune
+    );
+    assert_eq!(
+        ScaleMode::ExaggeratedRadii.radius(BodyKind::Star, SUN_RADIUS),
+        SUN_RADIUS
+    );
+    let orrery = ScaleMode::Orrery.orbit_offset(neptune, BodyKind::Star, SUN_RADIUS);
+    assert!(orrery.length() < Length::from_au(10.0).as_units());
+}
@@ -2,14 +2,29 @@ use bevy::prelude::*;
 use heron::*;
 
 use crate::{
-    consts::KILOMETER,
-    prelude::KM_TO_UNIT,
     property::{PropertyUpdateEvent, PropertyValue},
+    quantity,
+    soi::{DominantBody, SphereOfInfluence},
+    universal_position::UniversalPosition,
 };
 
 #[derive(Component)]
 pub struct Ship {}
 
+/// thrust acceleration in render units (AU) per second squared, about 1500 km/s^2
+const LIN_ACCEL: f32 = 1e-5;
+/// half the size of the ship's collision box
+const HALF_SIZE: quantity::Length = quantity::Length::from_km(0.02);
+
+/// The ship's collider in render units, a box with rounded edges
+pub fn collision_shape() -> CollisionShape {
+    let half_size = HALF_SIZE.as_units() as f32;
+    CollisionShape::Cuboid {
+        half_extends: Vec3::splat(half_size),
+        border_radius: Some(half_size),
+    }
+}
+
 pub fn acceleration_system(
     keyboard_input: Res<Input<KeyCode>>,
     mut query: Query<
@@ -22,7 +37,6 @@ pub fn acceleration_system(
         With<Ship>,
     >,
 ) {
-    const LIN_ACCEL: f32 = 0.01 / KILOMETER;
     for (mut acceleration, mut velocity, mut transform, global_transform) in query.iter_mut() {
         let forward = global_transform.rotation * Vec3::Z;
         let up = global_transform.rotation * Vec3::Y;
@@ -60,28 +74,51 @@ pub fn acceleration_system(
     }
 }
 
+// Position and velocity are relative to the body whose sphere of influence the ship is in, see
+// `soi`. The position is true, the velocity is taken in render space against the drawn body: ships
+// keep their true offset to the nearest drawn body (see `universal_position`), so near the dominant
+// body this is the true relative velocity in every scale mode.
 pub fn update_properties_system(
     mut property_update_events: EventWriter<PropertyUpdateEvent>,
-    mut query: Query<
-        (
-            &mut Acceleration,
-            &mut Velocity,
-            &mut Transform,
-            &GlobalTransform,
-        ),
-        With<Ship>,
-    >,
+    body_query: Query<(&UniversalPosition, &SphereOfInfluence)>,
+    query: Query<(&Velocity, &UniversalPosition, Option<&DominantBody>), With<Ship>>,
 ) {
-    for (mut acceleration, mut velocity, mut transform, global_transform) in query.iter_mut() {
-        let vel = velocity.linear.length();
+    for (velocity, position, dominant) in query.iter() {
+        let reference = dominant.and_then(|dominant| body_query.get(dominant.body).ok());
+        let (relative_position, relative_velocity) = match reference {
+            Some((body_position, soi)) => (
+                position.0 - body_position.0,
+                velocity.linear.as_dvec3() - soi.velocity,
+            ),
+            None => (position.0, velocity.linear.as_dvec3()),
+        };
+        let vel = quantity::Velocity::from_units_per_s(relative_velocity.length());
 
         property_update_events.send(PropertyUpdateEvent::new(
             "ship.velocity".to_string(),
-            PropertyValue::Float(vel / KM_TO_UNIT),
+            PropertyValue::Velocity(vel),
         ));
         property_update_events.send(PropertyUpdateEvent::new(
             "ship.position".to_string(),
-            PropertyValue::Vec3(global_transform.translation),
+            PropertyValue::Vec3(relative_position.as_vec3()),
+        ));
+        property_update_events.send(PropertyUpdateEvent::new(
+            "ship.reference".to_string(),
+            PropertyValue::String(reference.map_or_else(String::new, |(_, soi)| soi.name.clone())),
         ));
     }
 }
+
+#[test]
+fn test_collision_shape() {
+    let reach = match collision_shape() {
+        CollisionShape::Cuboid {
+            half_extends,
+            border_radius,
+        } => half_extends.length() + border_radius.unwrap_or(0.0),
+        _ => unreachable!(),
+    };
+    // the largest belt rock 10 000 km away is well clear of the ship
+    let rock = quantity::Length::from_km(470.0);
+    assert!(quantity::Length::from_units(reach as f64) + rock < quantity::Length::from_km(1e4));
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use crate::{
+    property::{PropertyUpdateEvent, PropertyValue},
+    quantity::{Duration, SECONDS_PER_DAY},
+};
+
+/// Julian date of the J2000 epoch
+pub const J2000: f64 = 2_451_545.0;
+
+pub const WARP_STEPS: [f64; 7] = [1.0, 10.0, 1e2, 1e3, 1e4, 1e5, 1e6];
+
+/// Simulation time, decoupled from wall-clock `Time`. All orbit and spin systems advance from this.
+pub struct SimClock {
+    /// julian date of t = 0
+    pub epoch: f64,
+    /// seconds since epoch
+    pub time: f64,
+    /// simulation seconds per wall-clock second
+    pub warp: f64,
+    pub paused: bool,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock {
+            epoch: J2000,
+            time: 0.0,
+            warp: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl SimClock {
+    pub fn advance(&mut self, wall_clock_seconds: f64) {
+        if !self.paused {
+            self.time += wall_clock_seconds * self.warp;
+        }
+    }
+
+    /// days since epoch (the time unit of orbital periods)
+    pub fn days(&self) -> f64 {
+        self.time / SECONDS_PER_DAY
+    }
+
+    pub fn julian_date(&self) -> f64 {
+        self.epoch + self.days()
+    }
+
+    /// applies a sim.* property coming from the hud, ignoring unrelated or unchanged values
+    pub fn apply_property(&mut self, name: &str, value: &PropertyValue) {
+        match (name, value) {
+            ("sim.warp", PropertyValue::Float(warp)) if *warp as f64 != self.warp => {
+                self.warp = (*warp as f64).max(0.0);
+            }
+            ("sim.paused", PropertyValue::Bool(paused)) if *paused != self.paused => {
+                self.paused = *paused;
+            }
+            _ => (),
+        }
+    }
+}
+
+pub fn sim_clock_system(time: Res<Time>, mut clock: ResMut<SimClock>) {
+    clock.advance(time.delta_seconds_f64());
+}
+
+pub fn update_properties_system(
+    clock: Res<SimClock>,
+    mut published: Local<Option<(f64, bool)>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    property_update_events.send(PropertyUpdateEvent::new(
+        "sim.time".to_string(),
+        PropertyValue::Duration(Duration::from_days(clock.days())),
+    ));
+
+    // only publish on change, otherwise stale values would race with updates coming from the hud
+    // (change detection on SimClock is useless for this, it is mutated every frame)
+    if *published != Some((clock.warp, clock.paused)) {
+        *published = Some((clock.warp, clock.paused));
+        property_update_events.send(PropertyUpdateEvent::new(
+            "sim.warp".to_string(),
+            PropertyValue::Float(clock.warp as f32),
+        ));
+        property_update_events.send(PropertyUpdateEvent::new(
+            "sim.paused".to_string(),
+            PropertyValue::Bool(clock.paused),
+        ));
+    }
+}
+
+pub fn apply_property_updates_system(
+    mut events: EventReader<PropertyUpdateEvent>,
+    mut clock: ResMut<SimClock>,
+) {
+    for event in events.iter() {
+        clock.apply_property(event.name(), event.value());
+    }
+}
+
+#[derive(Default)]
+pub struct SimClockPlugin;
+
+impl Plugin for SimClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimClock>()
+            .add_system(sim_clock_system.system())
+            .add_system(update_properties_system.system())
+            .add_system(apply_property_updates_system.system());
+    }
+}
+
+#[test]
+fn test_sim_clock() {
+    let mut clock = SimClock::default();
+    assert_eq!(clock.julian_date(), J2000);
+
+    clock.apply_property("sim.warp", &PropertyValue::Float(1e3));
+    clock.advance(86.4);
+    assert_eq!(clock.days(), 1.0);
+    assert_eq!(clock.julian_date(), J2000 + 1.0);
+
+    clock.apply_property("sim.paused", &PropertyValue::Bool(true));
+    clock.advance(86.4);
+    assert_eq!(clock.days(), 1.0);
+
+    clock.apply_property("sim.paused", &PropertyValue::Bool(false));
+    clock.apply_property("sim.warp", &PropertyValue::Float(-10.0));
+    assert_eq!(clock.warp, 0.0);
+    clock.apply_property("view.scale", &PropertyValue::Bool(true));
+    assert!(!clock.paused);
+}
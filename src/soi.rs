@@ -0,0 +1,256 @@
+//! Spheres of influence.
+//!
+//! Patched conics: within the sphere of influence of a body (see `Body::sphere_of_influence`) a ship
+//! moves relative to that body, outside of it relative to the body's parent. The root of the system
+//! dominates everywhere. Every ship is tagged with its `DominantBody`, found by descending from the
+//! root into the satellites whose spheres contain the ship, and a `SoiCrossingEvent` is sent when it
+//! changes. The `ship.position` and `ship.velocity` readouts are relative to the dominant body.
+//!
+//! Bodies without a sphere (no mass, or a parent without mass) are left out, and so are their
+//! satellites: they are only reachable through their parent.
+use bevy::{math::DVec3, prelude::*, transform::TransformSystem};
+
+use crate::{
+    quantity::Length,
+    ship::Ship,
+    universal_position::{DisplayPosition, UniversalPosition},
+    Body,
+};
+
+/// Sphere of influence of a spawned body
+#[derive(Component, Clone, Debug)]
+pub struct SphereOfInfluence {
+    pub name: String,
+    /// the Center the body orbits, None for the root of the system
+    pub parent: Option<Entity>,
+    /// None for the root
+    pub radius: Option<Length>,
+    /// velocity of the drawn body (see `DisplayPosition`) in render units per wall-clock second, like
+    /// the physics velocity of ships. Simulation time runs at the warp rate.
+    pub velocity: DVec3,
+    /// displayed position in the previous frame
+    previous: Option<DVec3>,
+}
+
+impl SphereOfInfluence {
+    pub fn root(body: &Body) -> Self {
+        SphereOfInfluence {
+            name: body.name.clone(),
+            parent: None,
+            radius: None,
+            velocity: DVec3::ZERO,
+            previous: None,
+        }
+    }
+
+    /// None if the body or its parent has no mass
+    pub fn new(body: &Body, parent: Entity, parent_body: &Body) -> Option<Self> {
+        Some(SphereOfInfluence {
+            parent: Some(parent),
+            radius: Some(body.sphere_of_influence(parent_body)?),
+            ..SphereOfInfluence::root(body)
+        })
+    }
+}
+
+/// The body whose sphere of influence a ship is in
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct DominantBody {
+    pub body: Entity,
+    pub name: String,
+}
+
+/// A ship moved into the sphere of influence of another body
+#[derive(Clone, Debug)]
+pub struct SoiCrossingEvent {
+    pub ship: Entity,
+    /// None when the ship was just spawned
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// A sphere of influence for `dominant`, the parent is an index into the same slice
+#[derive(Clone, Copy, Debug)]
+pub struct SoiNode {
+    pub parent: Option<usize>,
+    pub position: DVec3,
+    pub radius: Option<Length>,
+}
+
+/// Index of the node dominating `position` (true positions), descending from the node `root`.
+/// Where spheres of sibling bodies overlap the nearer body wins.
+pub fn dominant(position: DVec3, root: usize, nodes: &[SoiNode]) -> usize {
+    let mut current = root;
+    loop {
+        let inside = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.parent == Some(current))
+            .filter_map(|(index, node)| {
+                let distance = Length::from_units(node.position.distance(position));
+                if distance < node.radius? {
+                    Some((index, distance))
+                } else {
+                    None
+                }
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        match inside {
+            Some((index, _)) => current = index,
+            None => return current,
+        }
+    }
+}
+
+// runs once the true positions of bodies and ships are updated
+pub fn soi_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut body_query: Query<(
+        Entity,
+        &mut SphereOfInfluence,
+        &UniversalPosition,
+        &DisplayPosition,
+    )>,
+    ship_query: Query<(Entity, &UniversalPosition, Option<&DominantBody>), With<Ship>>,
+    mut crossing_events: EventWriter<SoiCrossingEvent>,
+) {
+    let dt = time.delta_seconds_f64();
+    for (_, mut soi, _, display) in body_query.iter_mut() {
+        if let Some(previous) = soi.previous {
+            if dt > 0.0 {
+                soi.velocity = (display.0 - previous) / dt;
+            }
+        }
+        soi.previous = Some(display.0);
+    }
+
+    let entities: Vec<_> = body_query.iter().map(|(entity, ..)| entity).collect();
+    let nodes: Vec<_> = body_query
+        .iter()
+        .map(|(_, soi, position, _)| SoiNode {
+            // None for satellites of bodies without a sphere, they are never reached
+            parent: soi
+                .parent
+                .and_then(|parent| entities.iter().position(|entity| *entity == parent)),
+            position: position.0,
+            radius: soi.radius,
+        })
+        .collect();
+    let root = match body_query
+        .iter()
+        .position(|(_, soi, ..)| soi.parent.is_none())
+    {
+        Some(root) => root,
+        None => return,
+    };
+    for (ship, position, current) in ship_query.iter() {
+        let body = entities[dominant(position.0, root, &nodes)];
+        if current.map(|current| current.body) == Some(body) {
+            continue;
+        }
+        let name = match body_query.get(body) {
+            Ok((_, soi, ..)) => soi.name.clone(),
+            Err(_) => continue,
+        };
+        let from = current.map(|current| current.name.clone());
+        info!(
+            "entering the sphere of influence of {} (from {:?})",
+            name, from
+        );
+        commands.entity(ship).insert(DominantBody {
+            body,
+            name: name.clone(),
+        });
+        crossing_events.send(SoiCrossingEvent {
+            ship,
+            from,
+            to: name,
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct SoiPlugin;
+
+impl Plugin for SoiPlugin {
+    fn build(&self, app: &mut App) {
+        // the ship's true position is derived from its transform before the propagation, see
+        // `universal_position`
+        app.add_event::<SoiCrossingEvent>().add_system_to_stage(
+            CoreStage::PostUpdate,
+            soi_system
+                .system()
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[test]
+fn test_dominant() {
+    let sun: Body = serde_yaml::from_str(
+        r#"
+name: sun
+orbit: 0
+orbit_time: 0
+gm: 1.327e11
+satellites:
+  - name: earth
+    orbit: 1.0
+    orbit_time: 365.25
+    mass: 5.972e24 kg
+    satellites:
+      - name: moon
+        orbit: 384400 km
+        orbit_time: 27.3
+        mass: 7.342e22 kg
+  - name: station
+    orbit: 1.5
+    orbit_time: 670
+"#,
+    )
+    .unwrap();
+    let earth = &sun.satellites[0];
+    let moon = &earth.satellites[0];
+    // without mass
+    assert_eq!(sun.satellites[1].sphere_of_influence(&sun), None);
+    let earth_soi = earth.sphere_of_influence(&sun).unwrap();
+    let moon_soi = moon.sphere_of_influence(earth).unwrap();
+    // ~66 thousand km
+    assert!((moon_soi.as_km() - 6.6e4).abs() < 0.1e4);
+
+    let earth_position = DVec3::new(1.0, 0.0, 0.0);
+    let moon_position = earth_position + DVec3::new(moon.orbit.as_units(), 0.0, 0.0);
+    let nodes = [
+        SoiNode {
+            parent: None,
+            position: DVec3::ZERO,
+            radius: None,
+        },
+        SoiNode {
+            parent: Some(0),
+            position: earth_position,
+            radius: Some(earth_soi),
+        },
+        SoiNode {
+            parent: Some(1),
+            position: moon_position,
+            radius: Some(moon_soi),
+        },
+    ];
+    let near =
+        |position: DVec3, km: f64| position + DVec3::new(0.0, Length::from_km(km).as_units(), 0.0);
+    assert_eq!(dominant(near(earth_position, 1e4), 0, &nodes), 1);
+    assert_eq!(dominant(near(moon_position, 1e4), 0, &nodes), 2);
+    assert_eq!(dominant(near(earth_position, 2e6), 0, &nodes), 0);
+    assert_eq!(dominant(DVec3::new(0.0, 5.0, 0.0), 0, &nodes), 0);
+    // a moon whose planet has no sphere is not reachable, even from inside its own sphere
+    let orphaned = [
+        SoiNode {
+            parent: None,
+            ..nodes[2]
+        },
+        nodes[0],
+    ];
+    assert_eq!(dominant(near(moon_position, 1e4), 1, &orphaned), 1);
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+
+use crate::{
+    property::{PropertyUpdateEvent, PropertyValue},
+    system_file, Body,
+};
+
+/// where `AssetServer` reads files from
+const ASSET_FOLDER: &str = "assets";
+
+/// Why the last load of a system file failed, by normalized path. Bevy only logs loader errors,
+/// this is shared with the loader so the HUD can show them.
+#[derive(Clone, Default)]
+pub struct SystemLoadErrors(Arc<Mutex<HashMap<PathBuf, String>>>);
+
+impl SystemLoadErrors {
+    pub fn get(&self, path: &Path) -> Option<String> {
+        let errors = self.0.lock().unwrap();
+        errors.get(&system_file::normalize(path)).cloned()
+    }
+
+    fn set(&self, path: PathBuf, error: Option<String>) {
+        let mut errors = self.0.lock().unwrap();
+        match error {
+            Some(error) => errors.insert(path, error),
+            None => errors.remove(&path),
+        };
+    }
+}
+
+/// A file included by system files. `BodyLoader` reads its text, it is only loaded as an asset so
+/// that it is watched for changes.
+#[derive(TypeUuid)]
+#[uuid = "3c8e1f27-5b9d-4a06-b2e4-7d1a9c6f0e58"]
+pub struct SystemInclude;
+
+#[derive(Default)]
+pub struct IncludeLoader;
+
+impl AssetLoader for IncludeLoader {
+    fn load<'a>(
+        &'a self,
+        _bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            load_context.set_default_asset(LoadedAsset::new(SystemInclude));
+            Ok(())
+        })
+    }
+
+    // system files themselves match the longer `system.yaml`
+    fn extensions(&self) -> &[&str] {
+        &["yaml"]
+    }
+}
+
+/// The files included by each loaded system file, by normalized path. The handles keep them loaded,
+/// so their changes arrive as `AssetEvent::Modified`.
+#[derive(Clone, Default)]
+pub struct SystemIncludes(Arc<Mutex<HashMap<PathBuf, Vec<Handle<SystemInclude>>>>>);
+
+impl SystemIncludes {
+    /// the system files including the file of `handle`
+    fn including(&self, handle: &Handle<SystemInclude>) -> Vec<PathBuf> {
+        let includes = self.0.lock().unwrap();
+        includes
+            .iter()
+            .filter(|(_, handles)| handles.contains(handle))
+            .map(|(root, _)| root.clone())
+            .collect()
+    }
+
+    fn set(&self, root: PathBuf, handles: Vec<Handle<SystemInclude>>) {
+        self.0.lock().unwrap().insert(root, handles);
+    }
+}
+
+/// Loads star-system descriptions (`*.system.yaml`) as `Body` assets.
+///
+/// Files pulled in through `$include` are read as well and registered as dependencies. Bevy only
+/// reloads the file that changed, so `reload_including_systems` resolves the system files including
+/// it again.
+pub struct BodyLoader {
+    errors: SystemLoadErrors,
+    includes: SystemIncludes,
+}
+
+impl FromWorld for BodyLoader {
+    fn from_world(world: &mut World) -> Self {
+        BodyLoader {
+            errors: world
+                .get_resource_or_insert_with(SystemLoadErrors::default)
+                .clone(),
+            includes: world
+                .get_resource_or_insert_with(SystemIncludes::default)
+                .clone(),
+        }
+    }
+}
+
+/// the body and the files it was read from
+async fn load_body(
+    root: &Path,
+    bytes: &[u8],
+    load_context: &mut LoadContext<'_>,
+) -> Result<(Body, Vec<PathBuf>), anyhow::Error> {
+    let mut sources = HashMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(file) = pending.pop() {
+        if sources.contains_key(&file) {
+            continue;
+        }
+        let text = if file == root {
+            String::from_utf8(bytes.to_vec())?
+        } else {
+            match load_context.read_asset_bytes(&file).await {
+                Ok(bytes) => String::from_utf8(bytes)?,
+                // missing includes are reported by resolve, with the location of the $include
+                Err(_) => continue,
+            }
+        };
+        pending.extend(system_file::includes(&file, &text)?);
+        sources.insert(file, text);
+    }
+    let body = system_file::resolve(root, &sources)?;
+    Ok((body, sources.into_keys().collect()))
+}
+
+impl AssetLoader for BodyLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let root = system_file::normalize(load_context.path());
+            let loaded = load_body(&root, bytes, load_context).await;
+            // the message carries file and line, see `system_file::SystemFileError`
+            self.errors.set(
+                root.clone(),
+                loaded.as_ref().err().map(|err| err.to_string()),
+            );
+            let (body, files) = loaded?;
+            let includes: Vec<_> = files.into_iter().filter(|file| *file != root).collect();
+            self.includes.set(
+                root,
+                includes
+                    .iter()
+                    .map(|file| load_context.get_handle(AssetPath::new_ref(file, None)))
+                    .collect(),
+            );
+            let dependencies = includes
+                .into_iter()
+                .map(|file| AssetPath::new(file, None))
+                .collect();
+            load_context.set_default_asset(LoadedAsset::new(body).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["system.yaml"]
+    }
+}
+
+/// Resolve the system files including a modified file again, from the filesystem
+pub fn reload_including_systems(
+    mut events: EventReader<AssetEvent<SystemInclude>>,
+    asset_server: Res<AssetServer>,
+    includes: Res<SystemIncludes>,
+    mut bodies: ResMut<Assets<Body>>,
+    mut property_update_events: EventWriter<PropertyUpdateEvent>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        for root in includes.including(handle) {
+            info!("an include changed, reloading {}", root.display());
+            let path = Path::new(ASSET_FOLDER).join(&root);
+            let loaded = system_file::read_sources(&path).and_then(|sources| {
+                let body = system_file::resolve(&path, &sources)?;
+                Ok((body, sources))
+            });
+            match loaded {
+                Ok((body, sources)) => {
+                    // includes may have been added or removed
+                    let handles = sources
+                        .into_keys()
+                        .filter_map(|file| {
+                            Some(file.strip_prefix(ASSET_FOLDER).ok()?.to_path_buf())
+                        })
+                        .filter(|file| *file != root)
+                        .map(|file| asset_server.load(file.as_path()))
+                        .collect();
+                    includes.set(root.clone(), handles);
+                    // sends AssetEvent::Modified, which respawns the system
+                    bodies.set_untracked(AssetPath::new(root, None), body);
+                }
+                Err(err) => {
+                    error!("{}", err);
+                    property_update_events.send(PropertyUpdateEvent::new(
+                        "system.errors".to_string(),
+                        PropertyValue::String(err.to_string()),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SystemAssetPlugin;
+
+impl Plugin for SystemAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Body>()
+            .add_asset::<SystemInclude>()
+            .init_resource::<SystemLoadErrors>()
+            .init_resource::<SystemIncludes>()
+            .init_asset_loader::<BodyLoader>()
+            .init_asset_loader::<IncludeLoader>()
+            .add_system(reload_including_systems.system());
+    }
+}
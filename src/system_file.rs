@@ -0,0 +1,552 @@
+//! Multi-file system descriptions.
+//!
+//! On top of the plain `Body` format, system files support
+//! * `$include: path.yaml` in place of any mapping. The mapping is replaced by the content of the
+//!   file (paths are relative to the including file). Other keys next to `$include` override keys
+//!   of the included mapping. If the included file contains a list and the `$include` is an element
+//!   of a list (e.g. `satellites`), the included elements are spliced in.
+//! * a top-level `templates` mapping (name -> partial body) in the root file. Bodies can use
+//!   `extends: <template>` and override individual keys. Templates can extend other templates.
+//!
+//! Overrides replace whole values, they are not merged recursively. A root body without `kind` is a
+//! star, as in files written before bodies had a kind.
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_yaml::{Mapping, Value};
+
+use crate::Body;
+
+const INCLUDE_KEY: &str = "$include";
+const ORIGIN_KEY: &str = "$origin";
+const TEMPLATES_KEY: &str = "templates";
+const EXTENDS_KEY: &str = "extends";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemFileError {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for SystemFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for SystemFileError {}
+
+/// Lexically resolve `.` and `..`, so the same file always maps to the same key.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+fn include_path(file: &Path, include: &str) -> PathBuf {
+    normalize(&file.parent().unwrap_or_else(|| Path::new("")).join(include))
+}
+
+/// 1-based number of the first line in `text` with the YAML key `key`, set to `value` if given.
+/// Keys of list items (`- key: value`) count, keys inside flow mappings do not.
+fn find_line(text: &str, key: &str, value: Option<&str>) -> Option<usize> {
+    text.lines()
+        .position(|line| {
+            let line = line.trim_start().trim_start_matches("- ").trim_start();
+            let rest = match line
+                .strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix(':'))
+            {
+                Some(rest) => rest,
+                None => return false,
+            };
+            // without a trailing comment and quotes
+            let rest = rest.split(" #").next().unwrap_or_default().trim();
+            value.map_or(true, |value| {
+                rest.trim_matches(|c| c == '"' || c == '\'') == value
+            })
+        })
+        .map(|i| i + 1)
+}
+
+fn parse(file: &Path, text: &str) -> Result<Value, SystemFileError> {
+    serde_yaml::from_str(text).map_err(|err| SystemFileError {
+        file: file.to_path_buf(),
+        line: err.location().map(|location| location.line()),
+        message: err.to_string(),
+    })
+}
+
+fn include_target(mapping: &Mapping) -> Option<&str> {
+    mapping
+        .get(&Value::from(INCLUDE_KEY))
+        .and_then(|target| target.as_str())
+}
+
+/// All files directly included by `file`, with paths resolved relative to it.
+pub fn includes(file: &Path, text: &str) -> Result<Vec<PathBuf>, SystemFileError> {
+    fn collect(value: &Value, file: &Path, out: &mut Vec<PathBuf>) {
+        match value {
+            Value::Mapping(mapping) => {
+                if let Some(target) = include_target(mapping) {
+                    out.push(include_path(file, target));
+                }
+                for (_, value) in mapping.iter() {
+                    collect(value, file, out);
+                }
+            }
+            Value::Sequence(sequence) => {
+                for value in sequence.iter() {
+                    collect(value, file, out);
+                }
+            }
+            _ => (),
+        }
+    }
+    let mut out = Vec::new();
+    collect(&parse(file, text)?, file, &mut out);
+    Ok(out)
+}
+
+struct Resolver<'a> {
+    sources: &'a HashMap<PathBuf, String>,
+    include_stack: Vec<PathBuf>,
+    templates: Mapping,
+}
+
+impl<'a> Resolver<'a> {
+    /// an error at the first `key` (with `value`) in `file`
+    fn error(
+        &self,
+        file: &Path,
+        key: &str,
+        value: Option<&str>,
+        message: String,
+    ) -> SystemFileError {
+        let line = self
+            .sources
+            .get(file)
+            .and_then(|text| find_line(text, key, value));
+        SystemFileError {
+            file: file.to_path_buf(),
+            line,
+            message,
+        }
+    }
+
+    fn load(&mut self, from: &Path, target: &str) -> Result<Value, SystemFileError> {
+        let file = include_path(from, target);
+        if self.include_stack.contains(&file) {
+            let cycle = self
+                .include_stack
+                .iter()
+                .chain(std::iter::once(&file))
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(self.error(
+                from,
+                INCLUDE_KEY,
+                Some(target),
+                format!("include cycle: {}", cycle),
+            ));
+        }
+        let text = match self.sources.get(&file) {
+            Some(text) => text,
+            None => {
+                let message = format!("cannot include {}: file not found", file.display());
+                return Err(self.error(from, INCLUDE_KEY, Some(target), message));
+            }
+        };
+        let value = parse(&file, text)?;
+        self.include_stack.push(file.clone());
+        let value = self.expand(value, &file);
+        self.include_stack.pop();
+        value
+    }
+
+    /// resolve all `$include`s in `value` (which was read from `file`)
+    fn expand(&mut self, value: Value, file: &Path) -> Result<Value, SystemFileError> {
+        match value {
+            Value::Mapping(mut mapping) => {
+                if let Some(target) = include_target(&mapping).map(str::to_string) {
+                    mapping.remove(&Value::from(INCLUDE_KEY));
+                    let included = self.load(file, &target)?;
+                    return match included {
+                        Value::Mapping(included) => {
+                            let overrides = self.expand(Value::Mapping(mapping), file)?;
+                            Ok(merge(included, overrides))
+                        }
+                        included if mapping.is_empty() => Ok(included),
+                        _ => Err(self.error(
+                            file,
+                            INCLUDE_KEY,
+                            Some(&target),
+                            format!("cannot override keys of {}: not a mapping", target),
+                        )),
+                    };
+                }
+
+                let mut expanded = Mapping::new();
+                for (key, value) in mapping {
+                    expanded.insert(key, self.expand(value, file)?);
+                }
+                // remember where bodies were defined, for error messages after merging everything
+                if let Some(name) = expanded.get(&Value::from("name")).and_then(|n| n.as_str()) {
+                    let line = self
+                        .sources
+                        .get(file)
+                        .and_then(|text| find_line(text, "name", Some(name)));
+                    let origin = match line {
+                        Some(line) => format!("{}:{}", file.display(), line),
+                        None => file.display().to_string(),
+                    };
+                    expanded.insert(Value::from(ORIGIN_KEY), Value::from(origin));
+                }
+                Ok(Value::Mapping(expanded))
+            }
+            Value::Sequence(sequence) => {
+                let mut expanded = Vec::new();
+                for value in sequence {
+                    let is_include =
+                        matches!(&value, Value::Mapping(m) if include_target(m).is_some());
+                    match self.expand(value, file)? {
+                        Value::Sequence(included) if is_include => expanded.extend(included),
+                        value => expanded.push(value),
+                    }
+                }
+                Ok(Value::Sequence(expanded))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn template(&self, name: &str, stack: &mut Vec<String>) -> Result<Mapping, String> {
+        if stack.iter().any(|n| n == name) {
+            stack.push(name.to_string());
+            return Err(format!("template cycle: {}", stack.join(" -> ")));
+        }
+        let template = match self.templates.get(&Value::from(name)) {
+            Some(Value::Mapping(template)) => template.clone(),
+            Some(_) => return Err(format!("template '{}' is not a mapping", name)),
+            None => return Err(format!("unknown template '{}'", name)),
+        };
+        stack.push(name.to_string());
+        let template = self.apply_extends(template, stack);
+        stack.pop();
+        template
+    }
+
+    fn apply_extends(
+        &self,
+        mut mapping: Mapping,
+        stack: &mut Vec<String>,
+    ) -> Result<Mapping, String> {
+        match mapping.remove(&Value::from(EXTENDS_KEY)) {
+            Some(Value::String(base)) => Ok(merge_mappings(self.template(&base, stack)?, mapping)),
+            Some(_) => Err(format!("'{}' must be a template name", EXTENDS_KEY)),
+            None => Ok(mapping),
+        }
+    }
+
+    /// apply templates to a body and (recursively) its satellites
+    fn instantiate(&self, body: Value, root: &Path) -> Result<Value, SystemFileError> {
+        let mapping = match body {
+            Value::Mapping(mapping) => mapping,
+            body => return Ok(body),
+        };
+        let origin = origin(&mapping);
+        let mut mapping = self
+            .apply_extends(mapping, &mut Vec::new())
+            .map_err(|message| origin_error(origin.as_deref(), root, message))?;
+        if let Some(Value::Sequence(satellites)) = mapping.remove(&Value::from("satellites")) {
+            let satellites = satellites
+                .into_iter()
+                .map(|satellite| self.instantiate(satellite, root))
+                .collect::<Result<Vec<_>, _>>()?;
+            mapping.insert(Value::from("satellites"), Value::Sequence(satellites));
+        }
+        Ok(Value::Mapping(mapping))
+    }
+}
+
+fn merge_mappings(mut base: Mapping, overrides: Mapping) -> Mapping {
+    for (key, value) in overrides {
+        base.insert(key, value);
+    }
+    base
+}
+
+fn merge(base: Mapping, overrides: Value) -> Value {
+    match overrides {
+        Value::Mapping(overrides) => Value::Mapping(merge_mappings(base, overrides)),
+        _ => Value::Mapping(base),
+    }
+}
+
+fn origin(mapping: &Mapping) -> Option<String> {
+    mapping
+        .get(&Value::from(ORIGIN_KEY))
+        .and_then(|origin| origin.as_str())
+        .map(str::to_string)
+}
+
+fn origin_error(origin: Option<&str>, root: &Path, message: String) -> SystemFileError {
+    let (file, line) = match origin.and_then(|origin| origin.rsplit_once(':')) {
+        Some((file, line)) => (PathBuf::from(file), line.parse().ok()),
+        None => (
+            origin.map_or_else(|| root.to_path_buf(), PathBuf::from),
+            None,
+        ),
+    };
+    SystemFileError {
+        file,
+        line,
+        message,
+    }
+}
+
+/// find the first body that does not deserialize, to report its origin
+fn locate_error(value: &Value, root: &Path) -> Option<SystemFileError> {
+    let mapping = value.as_mapping()?;
+    let satellites = mapping.get(&Value::from("satellites"));
+    if let Some(Value::Sequence(satellites)) = satellites {
+        if let Some(error) = satellites.iter().find_map(|s| locate_error(s, root)) {
+            return Some(error);
+        }
+    }
+    let mut shallow = mapping.clone();
+    shallow.insert(Value::from("satellites"), Value::Sequence(Vec::new()));
+    let err = serde_yaml::from_value::<Body>(Value::Mapping(shallow)).err()?;
+    Some(origin_error(
+        origin(mapping).as_deref(),
+        root,
+        err.to_string(),
+    ))
+}
+
+/// Resolve includes and templates of `root` and build the `Body` tree. `sources` must contain the
+/// text of `root` and of all (transitively) included files.
+pub fn resolve(root: &Path, sources: &HashMap<PathBuf, String>) -> Result<Body, SystemFileError> {
+    let root = normalize(root);
+    let mut resolver = Resolver {
+        sources,
+        include_stack: vec![root.clone()],
+        templates: Mapping::new(),
+    };
+    let text = sources.get(&root).ok_or_else(|| SystemFileError {
+        file: root.clone(),
+        line: None,
+        message: "file not found".into(),
+    })?;
+    let value = resolver.expand(parse(&root, text)?, &root)?;
+    let mut mapping = match value {
+        Value::Mapping(mapping) => mapping,
+        _ => {
+            return Err(SystemFileError {
+                file: root,
+                line: None,
+                message: "system file must contain a mapping".into(),
+            })
+        }
+    };
+    match mapping.remove(&Value::from(TEMPLATES_KEY)) {
+        Some(Value::Mapping(templates)) => resolver.templates = templates,
+        Some(_) => {
+            let message = format!("'{}' must be a mapping", TEMPLATES_KEY);
+            return Err(resolver.error(&root, TEMPLATES_KEY, None, message));
+        }
+        None => (),
+    }
+    let mut value = resolver.instantiate(Value::Mapping(mapping), &root)?;
+    // after the templates, which may give the kind
+    if let Value::Mapping(mapping) = &mut value {
+        if !mapping.contains_key(&Value::from("kind")) {
+            mapping.insert(Value::from("kind"), Value::from("star"));
+        }
+    }
+    serde_yaml::from_value(value.clone()).map_err(|err| {
+        locate_error(&value, &root).unwrap_or(SystemFileError {
+            file: root,
+            line: None,
+            message: err.to_string(),
+        })
+    })
+}
+
+/// Read a system file and everything it includes from the filesystem.
+pub fn load(path: &Path) -> anyhow::Result<Body> {
+    Ok(resolve(path, &read_sources(path)?)?)
+}
+
+/// The text of a system file and of everything it includes, by normalized path
+pub fn read_sources(path: &Path) -> anyhow::Result<HashMap<PathBuf, String>> {
+    let mut sources = HashMap::new();
+    let mut pending = vec![normalize(path)];
+    while let Some(file) = pending.pop() {
+        if sources.contains_key(&file) {
+            continue;
+        }
+        // missing includes are reported by resolve, with the location of the $include
+        if let Ok(text) = std::fs::read_to_string(&file) {
+            pending.extend(includes(&file, &text)?);
+            sources.insert(file, text);
+        }
+    }
+    Ok(sources)
+}
+
+#[cfg(test)]
+fn test_sources(files: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+    files
+        .iter()
+        .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_include_and_templates() {
+    let sources = test_sources(&[
+        (
+            "sol.system.yaml",
+            r#"
+templates:
+  $include: sol/templates.yaml
+name: sun
+kind: star
+orbit: 0
+orbit_time: 0
+satellites:
+  - $include: sol/jupiter.yaml
+    orbit_time: 4332.59
+  - $include: sol/spawn_points.yaml
+"#,
+        ),
+        (
+            "sol/templates.yaml",
+            r#"
+spawn: { kind: spawn_point, orbit_time: 1.0 }
+low_orbit_spawn: { extends: spawn, orbit: 0.0001 }
+"#,
+        ),
+        (
+            "sol/jupiter.yaml",
+            r#"
+name: jupiter
+orbit: 5.2
+orbit_time: 1.0
+radius: 69911
+satellites:
+  - $include: ./moons.yaml
+"#,
+        ),
+        (
+            "sol/moons.yaml",
+            r#"
+- { name: io, kind: moon, orbit: 421700 km, orbit_time: 1.77 }
+- { name: europa, kind: moon, orbit: 671034 km, orbit_time: 3.55 }
+"#,
+        ),
+        (
+            "sol/spawn_points.yaml",
+            r#"
+- { name: ship, extends: low_orbit_spawn }
+- { name: ship2, extends: low_orbit_spawn, orbit: 0.5 }
+"#,
+        ),
+    ]);
+
+    let sun = resolve(Path::new("sol.system.yaml"), &sources).unwrap();
+    let names: Vec<_> = sun.iter_tree().iter().map(|b| b.name.clone()).collect();
+    assert_eq!(names, ["sun", "jupiter", "io", "europa", "ship", "ship2"]);
+    assert_eq!(
+        sun.satellites[0].orbit_time,
+        crate::quantity::Duration::from_days(4332.59)
+    );
+    assert_eq!(sun.satellites[0].satellites[1].kind, crate::BodyKind::Moon);
+    assert_eq!(sun.satellites[1].kind, crate::BodyKind::SpawnPoint);
+    assert_eq!(
+        sun.satellites[1].orbit,
+        crate::quantity::Length::from_au(0.0001)
+    );
+    assert_eq!(
+        sun.satellites[2].orbit,
+        crate::quantity::Length::from_au(0.5)
+    );
+}
+
+#[test]
+fn test_include_errors() {
+    let cycle = test_sources(&[
+        (
+            "a.system.yaml",
+            "name: a\norbit: 0\norbit_time: 0\nsatellites:\n  - $include: b.yaml\n",
+        ),
+        (
+            "b.yaml",
+            "name: b\norbit: 1\norbit_time: 1\nsatellites:\n  - $include: a.system.yaml\n",
+        ),
+    ]);
+    let err = resolve(Path::new("a.system.yaml"), &cycle).unwrap_err();
+    assert_eq!(err.file, PathBuf::from("b.yaml"));
+    assert_eq!(err.line, Some(5));
+    assert!(err
+        .message
+        .contains("a.system.yaml -> b.yaml -> a.system.yaml"));
+
+    let missing = test_sources(&[(
+        "a.system.yaml",
+        "name: a\norbit: 0\norbit_time: 0\nsatellites:\n  - extends: nope\n    name: x\n",
+    )]);
+    let err = resolve(Path::new("a.system.yaml"), &missing).unwrap_err();
+    assert_eq!(err.to_string(), "a.system.yaml:6: unknown template 'nope'");
+
+    let invalid = test_sources(&[
+        (
+            "a.system.yaml",
+            "name: a\norbit: 0\norbit_time: 0\nsatellites:\n  - $include: b.yaml\n",
+        ),
+        ("b.yaml", "\nname: b\norbit: 1 parsec\norbit_time: 1\n"),
+    ]);
+    let err = resolve(Path::new("a.system.yaml"), &invalid).unwrap_err();
+    assert_eq!((err.file, err.line), (PathBuf::from("b.yaml"), Some(2)));
+    assert!(err.message.contains("parsec"));
+}
+
+#[test]
+fn test_find_line() {
+    let text =
+        "name: earth\nmoons: 1\nsatellites:\n  - name: earth_moon\n  - name: \"moon\" # the moon\n";
+    assert_eq!(find_line(text, "moon", None), None);
+    assert_eq!(find_line(text, "moons", None), Some(2));
+    assert_eq!(find_line(text, "name", Some("moon")), Some(5));
+    assert_eq!(find_line(text, "name", Some("earth")), Some(1));
+}
+
+#[test]
+fn test_root_kind() {
+    let sources = test_sources(&[(
+        "old.system.yaml",
+        "name: sun\norbit: 0\norbit_time: 0\nsatellites:\n  - { name: earth, orbit: 1, orbit_time: 365 }\n",
+    )]);
+    let sun = resolve(Path::new("old.system.yaml"), &sources).unwrap();
+    assert_eq!(sun.kind, crate::BodyKind::Star);
+    assert_eq!(sun.satellites[0].kind, crate::BodyKind::Planet);
+}
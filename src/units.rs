@@ -0,0 +1,271 @@
+//! Unit-annotated quantities in system files.
+//!
+//! Fields accept either bare numbers (interpreted in the canonical unit of the field) or strings
+//! like `"384400 km"`, `"1.0 AU"`, `"27.3 d"` or `"23.9 h"`. Everything is normalized to the
+//! canonical unit while loading, so serializing a `Body` again produces bare canonical numbers.
+use serde::{de, Deserialize, Deserializer, Serializer};
+use std::fmt;
+
+use crate::quantity::{Angle, Duration, Length, Mass, Quantity, KM_PER_AU, SECONDS_PER_DAY};
+
+pub struct Dimension {
+    pub name: &'static str,
+    /// unit symbols and their size in the base unit of the dimension
+    pub units: &'static [(&'static str, f64)],
+}
+
+/// base unit: km
+pub const LENGTH: Dimension = Dimension {
+    name: "length",
+    units: &[
+        ("m", 1e-3),
+        ("km", 1.0),
+        ("Mm", 1e3),
+        ("AU", KM_PER_AU),
+        ("au", KM_PER_AU),
+    ],
+};
+
+/// base unit: day
+pub const DURATION: Dimension = Dimension {
+    name: "duration",
+    units: &[
+        ("s", 1.0 / SECONDS_PER_DAY),
+        ("min", 1.0 / 1440.0),
+        ("h", 1.0 / 24.0),
+        ("d", 1.0),
+        ("y", 365.25),
+    ],
+};
+
+/// base unit: kg
+pub const MASS: Dimension = Dimension {
+    name: "mass",
+    units: &[
+        ("kg", 1.0),
+        ("t", 1e3),
+        ("M_earth", 5.9722e24),
+        ("M_jupiter", 1.898_13e27),
+        ("M_sun", 1.988_47e30),
+    ],
+};
+
+/// base unit: degree
+pub const ANGLE: Dimension = Dimension {
+    name: "angle",
+    units: &[("deg", 1.0), ("rad", 180.0 / std::f64::consts::PI)],
+};
+
+#[derive(Debug, PartialEq)]
+pub enum UnitError {
+    Malformed(String),
+    UnknownUnit {
+        dimension: &'static str,
+        unit: String,
+        input: String,
+        expected: Vec<&'static str>,
+    },
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::Malformed(input) => {
+                write!(
+                    f,
+                    "malformed quantity '{}', expected '<number> <unit>'",
+                    input
+                )
+            }
+            UnitError::UnknownUnit {
+                dimension,
+                unit,
+                input,
+                expected,
+            } => write!(
+                f,
+                "unknown {} unit '{}' in '{}' (expected one of {})",
+                dimension,
+                unit,
+                input,
+                expected.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+impl Dimension {
+    pub fn unit_size(&self, unit: &str) -> Option<f64> {
+        self.units
+            .iter()
+            .find(|(symbol, _)| *symbol == unit)
+            .map(|(_, size)| *size)
+    }
+
+    /// Parse `input` and convert it into `target` (one of the units of this dimension). Quantities
+    /// without unit are taken to be in `target` already.
+    pub fn parse(&self, input: &str, target: &str) -> Result<f64, UnitError> {
+        let target_size = self
+            .unit_size(target)
+            .expect("target unit not part of dimension");
+        let mut parts = input.split_whitespace();
+        let value: f64 = parts
+            .next()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| UnitError::Malformed(input.to_string()))?;
+        let unit = match (parts.next(), parts.next()) {
+            (None, _) => return Ok(value),
+            (Some(unit), None) => unit,
+            _ => return Err(UnitError::Malformed(input.to_string())),
+        };
+        let size = self.unit_size(unit).ok_or_else(|| UnitError::UnknownUnit {
+            dimension: self.name,
+            unit: unit.to_string(),
+            input: input.to_string(),
+            expected: self.units.iter().map(|(symbol, _)| *symbol).collect(),
+        })?;
+        Ok(value * size / target_size)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(f64),
+    String(String),
+}
+
+/// `value` given in `unit` (one of the units of the quantity's dimension)
+fn from_unit<Q: Quantity>(value: f64, unit: &str) -> Q {
+    let size = Q::DIMENSION
+        .unit_size(unit)
+        .expect("unit not part of dimension");
+    Q::from_base(value * size)
+}
+
+/// Parse `input` into a typed quantity. Quantities without unit are taken to be in `unit`.
+pub fn parse_quantity<Q: Quantity>(input: &str, unit: &str) -> Result<Q, UnitError> {
+    Ok(from_unit(Q::DIMENSION.parse(input, unit)?, unit))
+}
+
+fn to_quantity<Q: Quantity>(value: NumberOrString, unit: &str) -> Result<Q, UnitError> {
+    match value {
+        NumberOrString::Number(value) => Ok(from_unit(value, unit)),
+        NumberOrString::String(input) => parse_quantity(&input, unit),
+    }
+}
+
+fn deserialize_quantity<'de, D: Deserializer<'de>, Q: Quantity>(
+    deserializer: D,
+    target: &str,
+) -> Result<Q, D::Error> {
+    to_quantity(NumberOrString::deserialize(deserializer)?, target).map_err(de::Error::custom)
+}
+
+fn deserialize_optional_quantity<'de, D: Deserializer<'de>, Q: Quantity>(
+    deserializer: D,
+    target: &str,
+) -> Result<Option<Q>, D::Error> {
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(value) => to_quantity(value, target)
+            .map(Some)
+            .map_err(de::Error::custom),
+    }
+}
+
+// bare numbers in the target unit, at full precision
+fn serialize_quantity<S: Serializer, Q: Quantity>(
+    value: &Q,
+    serializer: S,
+    target: &str,
+) -> Result<S::Ok, S::Error> {
+    let size = Q::DIMENSION
+        .unit_size(target)
+        .expect("target unit not part of dimension");
+    serializer.serialize_f64(value.base() / size)
+}
+
+// field (de)serializers, for use with #[serde(with = "...")]. Bare numbers are in the unit the
+// module is named after.
+macro_rules! field_unit {
+    ($name:ident, $optional:ident, $quantity:ty, $unit:literal) => {
+        pub mod $name {
+            use super::*;
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<$quantity, D::Error> {
+                deserialize_quantity(deserializer, $unit)
+            }
+
+            pub fn serialize<S: Serializer>(
+                value: &$quantity,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serialize_quantity(value, serializer, $unit)
+            }
+        }
+
+        pub mod $optional {
+            use super::*;
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Option<$quantity>, D::Error> {
+                deserialize_optional_quantity(deserializer, $unit)
+            }
+
+            pub fn serialize<S: Serializer>(
+                value: &Option<$quantity>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                match value {
+                    Some(value) => serialize_quantity(value, serializer, $unit),
+                    None => serializer.serialize_none(),
+                }
+            }
+        }
+    };
+}
+
+field_unit!(au, optional_au, Length, "AU");
+field_unit!(km, optional_km, Length, "km");
+field_unit!(days, optional_days, Duration, "d");
+field_unit!(kg, optional_kg, Mass, "kg");
+field_unit!(degrees, optional_degrees, Angle, "deg");
+
+#[test]
+fn test_parse() {
+    assert_eq!(LENGTH.parse("1.0 AU", "km"), Ok(1.495_978_707e8));
+    assert_eq!(LENGTH.parse("1.495978707e8 km", "AU"), Ok(1.0));
+    assert_eq!(LENGTH.parse("384400", "km"), Ok(384400.0));
+    assert_eq!(DURATION.parse("36 h", "d"), Ok(1.5));
+    assert_eq!(MASS.parse("2 M_earth", "kg"), Ok(2.0 * 5.9722e24));
+    assert!((ANGLE.parse("3.14159265 rad", "deg").unwrap() - 180.0).abs() < 1e-6);
+    assert_eq!(
+        LENGTH.parse("3 pc", "km").unwrap_err().to_string(),
+        "unknown length unit 'pc' in '3 pc' (expected one of m, km, Mm, AU, au)"
+    );
+    assert!(matches!(
+        LENGTH.parse("km 3", "km"),
+        Err(UnitError::Malformed(_))
+    ));
+
+    // serialized at full precision, kilometres matter at AU scale
+    #[derive(serde::Serialize, Deserialize)]
+    struct Orbit {
+        #[serde(with = "au")]
+        orbit: Length,
+    }
+    let orbit = Orbit {
+        orbit: Length::from_km(778_547_200.0),
+    };
+    let yaml = serde_yaml::to_string(&orbit).unwrap();
+    assert_eq!(
+        serde_yaml::from_str::<Orbit>(&yaml).unwrap().orbit,
+        orbit.orbit
+    );
+}
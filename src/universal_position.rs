@@ -0,0 +1,118 @@
+//! Precise positions.
+//!
+//! `UniversalPosition` is the true position of an entity relative to the root of the star system, in
+//! f64 (which resolves about a millimeter at the orbit of Neptune). Render transforms are derived from
+//! it relative to the floating origin, which stays close to the camera. Ships are the exception:
+//! heron moves them in render space, so their `UniversalPosition` is derived from the transform.
+//!
+//! In scale modes other than realistic (see `scale`) bodies are drawn at a `DisplayPosition`, and the
+//! floating origin lives in the displayed system. Ships fly at true scale: their true position keeps
+//! the offset to the nearest body.
+//!
+//! The render transform is written to `Transform`, so entities with a `UniversalPosition` must be
+//! roots or children of untransformed nodes.
+use bevy::{math::DVec3, prelude::*, transform::TransformSystem};
+
+use crate::{floating_origin::FloatingOrigin, quantity::Length, ship::Ship};
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct UniversalPosition(pub DVec3);
+
+impl UniversalPosition {
+    pub fn distance(&self, other: &UniversalPosition) -> Length {
+        Length::from_units(self.0.distance(other.0))
+    }
+}
+
+/// Where a body is drawn, relative to the root of the star system
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayPosition(pub DVec3);
+
+fn nearest(
+    position: DVec3,
+    points: impl Iterator<Item = (DVec3, DVec3)>,
+) -> Option<(DVec3, DVec3)> {
+    points.min_by(|(a, _), (b, _)| {
+        a.distance_squared(position)
+            .partial_cmp(&b.distance_squared(position))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+/// Displayed position for the true `position`, at the same offset from the nearest anchor. Anchors
+/// are pairs of true and displayed positions.
+pub fn true_to_display(position: DVec3, anchors: &[(DVec3, DVec3)]) -> DVec3 {
+    nearest(position, anchors.iter().copied())
+        .map_or(position, |(anchor, display)| display + (position - anchor))
+}
+
+/// Inverse of `true_to_display`
+pub fn display_to_true(display: DVec3, anchors: &[(DVec3, DVec3)]) -> DVec3 {
+    nearest(
+        display,
+        anchors.iter().map(|(anchor, display)| (*display, *anchor)),
+    )
+    .map_or(display, |(anchor_display, anchor)| {
+        anchor + (display - anchor_display)
+    })
+}
+
+pub fn render_transform_system(
+    origin: Res<FloatingOrigin>,
+    mut query: Query<(&UniversalPosition, Option<&DisplayPosition>, &mut Transform), Without<Ship>>,
+) {
+    for (position, display, mut transform) in query.iter_mut() {
+        let position = display.map_or(position.0, |display| display.0);
+        transform.translation = origin.render_position(position);
+    }
+}
+
+pub fn ship_position_system(
+    origin: Res<FloatingOrigin>,
+    anchor_query: Query<(&UniversalPosition, &DisplayPosition), Without<Ship>>,
+    mut query: Query<(&Transform, &mut UniversalPosition), With<Ship>>,
+) {
+    let anchors: Vec<_> = anchor_query
+        .iter()
+        .map(|(position, display)| (position.0, display.0))
+        .collect();
+    for (transform, mut position) in query.iter_mut() {
+        position.0 = display_to_true(origin.true_position(transform.translation), &anchors);
+    }
+}
+
+#[derive(Default)]
+pub struct UniversalPositionPlugin;
+
+impl Plugin for UniversalPositionPlugin {
+    fn build(&self, app: &mut App) {
+        // after everything in Update moved bodies or rebased the origin, before the transforms are
+        // propagated
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            render_transform_system
+                .system()
+                .before(TransformSystem::TransformPropagate),
+        )
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            ship_position_system
+                .system()
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[test]
+fn test_display_mapping() {
+    // a planet drawn at a tenth of its distance, and its moon
+    let anchors = [
+        (DVec3::new(10.0, 0.0, 0.0), DVec3::new(1.0, 0.0, 0.0)),
+        (DVec3::new(10.0, 0.01, 0.0), DVec3::new(1.0, 0.5, 0.0)),
+    ];
+    let ship = DVec3::new(10.001, 0.0, 0.0);
+    let display = true_to_display(ship, &anchors);
+    assert!(display.abs_diff_eq(DVec3::new(1.001, 0.0, 0.0), 1e-12));
+    assert!(display_to_true(display, &anchors).abs_diff_eq(ship, 1e-12));
+    assert_eq!(true_to_display(ship, &[]), ship);
+}
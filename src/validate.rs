@@ -0,0 +1,288 @@
+//! Sanity checks for star-system descriptions. Every problem is reported with the YAML path of the
+//! offending field, e.g. `sun.satellites[3].satellites[0].radius`. Missing appearance files are
+//! only warnings: such bodies are drawn procedurally.
+use std::{collections::HashMap, fmt, path::Path};
+
+use crate::{
+    belt::Belt,
+    quantity::{Duration, Length, Mass},
+    Body, BodyKind,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// the system can be spawned anyway
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl ValidationError {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "{}: warning: {}", self.path, self.message),
+            Severity::Error => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+struct Validator<'a> {
+    asset_root: &'a Path,
+    names: HashMap<&'a str, String>,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, path: &str, field: &str, message: String, severity: Severity) {
+        self.errors.push(ValidationError {
+            path: format!("{}.{}", path, field),
+            message,
+            severity,
+        });
+    }
+
+    fn error(&mut self, path: &str, field: &str, message: String) {
+        self.report(path, field, message, Severity::Error);
+    }
+
+    fn warning(&mut self, path: &str, field: &str, message: String) {
+        self.report(path, field, message, Severity::Warning);
+    }
+
+    fn visit(&mut self, body: &'a Body, parent: Option<&'a Body>, path: &str) {
+        match self.names.get(body.name.as_str()) {
+            Some(first) => {
+                let message = format!("duplicate name '{}' (first used at {})", body.name, first);
+                self.error(path, "name", message);
+            }
+            None => {
+                self.names.insert(&body.name, path.to_string());
+            }
+        }
+
+        if body.radius < Length::ZERO {
+            self.error(path, "radius", format!("negative radius {}", body.radius));
+        }
+
+        if let Some(mass) = body.mass.filter(|mass| *mass <= Mass::ZERO) {
+            self.error(path, "mass", format!("mass must be positive, got {}", mass));
+        }
+        if body.gm.map_or(false, |gm| gm <= 0.0) {
+            self.error(
+                path,
+                "gm",
+                format!("gm must be positive, got {:?}", body.gm),
+            );
+        }
+        if body
+            .albedo
+            .map_or(false, |albedo| !(0.0..=1.0).contains(&albedo))
+        {
+            let message = format!("albedo must be in [0, 1], got {:?}", body.albedo);
+            self.error(path, "albedo", message);
+        }
+        if body.start && body.kind != BodyKind::SpawnPoint {
+            let message = format!(
+                "only spawn points can be the start, not a {}",
+                body.kind.name()
+            );
+            self.error(path, "start", message);
+        }
+
+        if let Some(model) = body.appearance.model_path() {
+            if !self.asset_root.join(&model).exists() {
+                self.warning(
+                    path,
+                    "appearance",
+                    format!("missing appearance file {:?}", model),
+                );
+            }
+        }
+        if let Some(clouds) = &body.appearance.clouds {
+            let texture = Path::new("models").join(&clouds.texture);
+            if !self.asset_root.join(&texture).exists() {
+                let message = format!("missing cloud texture {:?}", texture);
+                self.warning(path, "appearance.clouds", message);
+            }
+        }
+        if body.appearance.scale <= 0.0 {
+            let message = format!("scale must be positive, got {}", body.appearance.scale);
+            self.error(path, "appearance.scale", message);
+        }
+
+        if let Some(parent) = parent {
+            if body.orbit_time <= Duration::ZERO {
+                let message = format!("orbit_time must be positive, got {}", body.orbit_time);
+                self.error(path, "orbit_time", message);
+            }
+            if !(0.0..1.0).contains(&body.eccentricity) {
+                let message = format!("eccentricity must be in [0, 1), got {}", body.eccentricity);
+                self.error(path, "eccentricity", message);
+            }
+            let periapsis = body.orbit * (1.0 - body.eccentricity as f64);
+            if periapsis <= parent.radius {
+                let message = format!(
+                    "orbit {} passes inside the radius {} of '{}'",
+                    body.orbit, parent.radius, parent.name
+                );
+                self.error(path, "orbit", message);
+            }
+        }
+
+        for (i, satellite) in body.satellites.iter().enumerate() {
+            self.visit(
+                satellite,
+                Some(body),
+                &format!("{}.satellites[{}]", path, i),
+            );
+        }
+        for (i, belt) in body.belts.iter().enumerate() {
+            self.visit_belt(belt, body, &format!("{}.belts[{}]", path, i));
+        }
+    }
+
+    fn visit_belt(&mut self, belt: &'a Belt, parent: &'a Body, path: &str) {
+        match self.names.get(belt.name.as_str()) {
+            Some(first) => {
+                let message = format!("duplicate name '{}' (first used at {})", belt.name, first);
+                self.error(path, "name", message);
+            }
+            None => {
+                self.names.insert(&belt.name, path.to_string());
+            }
+        }
+
+        if parent.gm().is_none() {
+            let message = format!("'{}' needs a mass or gm to carry a belt", parent.name);
+            self.error(path, "name", message);
+        }
+        if belt.inner > belt.outer {
+            let message = format!(
+                "inner edge {} is beyond outer edge {}",
+                belt.inner, belt.outer
+            );
+            self.error(path, "inner", message);
+        }
+        if belt.inner <= parent.radius {
+            let message = format!(
+                "inner edge {} is inside the radius {} of '{}'",
+                belt.inner, parent.radius, parent.name
+            );
+            self.error(path, "inner", message);
+        }
+        if belt.count == 0 {
+            self.error(path, "count", "belt without rocks".to_string());
+        }
+        if belt.size.min <= Length::ZERO || belt.size.min > belt.size.max {
+            let message = format!(
+                "size range must satisfy 0 < min <= max, got {}..{}",
+                belt.size.min, belt.size.max
+            );
+            self.error(path, "size", message);
+        }
+        if !belt.appearance.is_empty() {
+            let model = Path::new("models").join(&belt.appearance);
+            if !self.asset_root.join(&model).exists() {
+                let message = format!("missing appearance file {:?}", model);
+                self.warning(path, "appearance", message);
+            }
+        }
+        if !(0.0..1.0).contains(&belt.max_eccentricity) {
+            let message = format!(
+                "max_eccentricity must be in [0, 1), got {}",
+                belt.max_eccentricity
+            );
+            self.error(path, "max_eccentricity", message);
+        }
+    }
+}
+
+/// Check the whole `Body` tree. Appearance files are looked up relative to `asset_root`. Only
+/// problems with `Severity::Error` keep the system from being spawned.
+pub fn validate(root: &Body, asset_root: &Path) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        asset_root,
+        names: HashMap::new(),
+        errors: Vec::new(),
+    };
+    validator.visit(root, None, &root.name);
+    validator.errors
+}
+
+#[test]
+fn test_validate() {
+    let sun: Body = serde_yaml::from_str(
+        r#"
+name: sun
+orbit: 0.0
+orbit_time: 0.0
+day: 0.0
+radius: 1400000.0
+appearance: none
+satellites:
+  - name: earth
+    orbit: 1.0
+    orbit_time: 365.0
+    day: 1.0
+    radius: -6100.0
+    appearance: earth/missing.gltf
+    satellites:
+      - name: moon
+        orbit: 0.001
+        orbit_time: 0.0
+        day: 31.0
+        radius: 1700.0
+        appearance: none
+        satellites: []
+  - name: earth
+    orbit: 2.0
+    orbit_time: 700.0
+    day: 1.0
+    radius: 6100.0
+    appearance: none
+    satellites:
+      - name: probe
+        orbit: 0.00001
+        orbit_time: 0.1
+        day: 0.0
+        radius: 0.0
+        appearance: none
+        start: true
+        satellites: []
+"#,
+    )
+    .unwrap();
+
+    let errors = validate(&sun, Path::new("assets"));
+    let paths: Vec<_> = errors.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "sun.satellites[0].radius",
+            "sun.satellites[0].appearance",
+            "sun.satellites[0].satellites[0].orbit_time",
+            "sun.satellites[1].name",
+            "sun.satellites[1].satellites[0].start",
+            "sun.satellites[1].satellites[0].orbit",
+        ]
+    );
+    // the missing model is only a warning
+    assert!(!errors[1].is_error());
+    assert_eq!(
+        errors[1].to_string(),
+        "sun.satellites[0].appearance: warning: missing appearance file \"models/earth/missing.gltf\""
+    );
+    assert_eq!(errors.iter().filter(|e| e.is_error()).count(), 5);
+}